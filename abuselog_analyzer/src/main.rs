@@ -49,7 +49,7 @@ async fn main_inner() -> color_eyre::Result<()> {
         hours,
     } = Args::parse();
     let site = ClientBuilder::enwiki()
-        .oauth(include_str!("../../oauth_alt.txt.secret"))
+        .credentials_from_env()?
         .user_agent("DeadbeefBot")
         .build()
         .await?;
@@ -0,0 +1,1046 @@
+//! The modules of `api.php`, answering parsed parameters from the state of the wiki.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+
+use crate::wiki::{
+    links, Base, Create, EditError, Page, Revision, Title, Wiki, ALIASES, NAMESPACES, NS_CATEGORY,
+    NS_FILE,
+};
+
+/// The most results a module returns at once, for `limit=max` and when no limit is given.
+const MAX_LIMIT: usize = 500;
+const DEFAULT_LIMIT: usize = 10;
+
+pub struct ApiError {
+    pub code: String,
+    pub info: String,
+}
+
+impl ApiError {
+    pub fn new(code: &str, info: impl Into<String>) -> Self {
+        Self {
+            code: code.to_owned(),
+            info: info.into(),
+        }
+    }
+
+    fn bad_value(param: &str, value: &str) -> Self {
+        Self::new(
+            "badvalue",
+            format!("Unrecognized value for parameter \"{param}\": {value}."),
+        )
+    }
+
+    fn missing(param: &str) -> Self {
+        Self::new(
+            "missingparam",
+            format!("The \"{param}\" parameter must be set."),
+        )
+    }
+
+    pub fn into_value(self) -> Value {
+        json!({
+            "error": {
+                "code": self.code,
+                "info": self.info,
+                "docref": "See /w/api.php for API usage.",
+            },
+            "servedby": "fakewiki",
+        })
+    }
+}
+
+type Result<T, E = ApiError> = std::result::Result<T, E>;
+
+/// The parameters of a request, from both the query string and the body.
+#[derive(Default, Debug)]
+pub struct Params(HashMap<String, String>);
+
+impl FromIterator<(String, String)> for Params {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    fn require(&self, name: &str) -> Result<&str> {
+        self.get(name).ok_or_else(|| ApiError::missing(name))
+    }
+
+    /// Boolean parameters are true when present, whatever their value.
+    fn flag(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// A multi-value parameter, separated by `|`, or by U+001F if it starts with one.
+    fn list(&self, name: &str) -> Vec<&str> {
+        match self.get(name) {
+            None | Some("") => Vec::new(),
+            Some(v) => match v.strip_prefix('\u{1f}') {
+                Some(v) => v.split('\u{1f}').collect(),
+                None => v.split('|').collect(),
+            },
+        }
+    }
+
+    fn has(&self, name: &str, value: &str) -> bool {
+        self.list(name).contains(&value)
+    }
+
+    fn int(&self, name: &str) -> Result<Option<u64>> {
+        self.get(name)
+            .map(|v| v.parse().map_err(|_| ApiError::bad_value(name, v)))
+            .transpose()
+    }
+
+    /// A multi-value parameter of namespace ids. Empty means all namespaces.
+    fn namespaces(&self, name: &str) -> Result<Vec<i64>> {
+        self.list(name)
+            .into_iter()
+            .map(|v| v.parse().map_err(|_| ApiError::bad_value(name, v)))
+            .collect()
+    }
+
+    fn limit(&self, name: &str) -> Result<usize> {
+        match self.get(name) {
+            None => Ok(DEFAULT_LIMIT),
+            Some("max") => Ok(MAX_LIMIT),
+            Some(v) => v
+                .parse()
+                .map(|n: usize| n.clamp(1, MAX_LIMIT))
+                .map_err(|_| ApiError::bad_value(name, v)),
+        }
+    }
+
+    fn timestamp(&self, wiki: &Wiki, name: &str) -> Result<Option<DateTime<Utc>>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some("now") => Ok(Some(wiki.now())),
+            Some(v) => DateTime::parse_from_rfc3339(v)
+                .map(|t| Some(t.with_timezone(&Utc)))
+                .map_err(|_| ApiError::bad_value(name, v)),
+        }
+    }
+
+    fn title(&self, name: &str) -> Result<Option<Title>> {
+        self.get(name)
+            .map(|v| {
+                Title::parse(v)
+                    .ok_or_else(|| ApiError::new("invalidtitle", format!("Bad title \"{v}\".")))
+            })
+            .transpose()
+    }
+}
+
+/// Who a request is made by.
+pub struct Caller {
+    pub session: String,
+    /// Set when the request carries an OAuth access token instead of a session cookie.
+    pub oauth_user: Option<String>,
+}
+
+impl Caller {
+    pub fn user(&self, wiki: &Wiki) -> Option<String> {
+        self.oauth_user
+            .clone()
+            .or_else(|| wiki.sessions.get(&self.session)?.user.clone())
+    }
+
+    /// The name shown for the caller: the user name, or an IP address when logged out.
+    fn name(&self, wiki: &Wiki) -> String {
+        self.user(wiki).unwrap_or_else(|| "127.0.0.1".to_owned())
+    }
+
+    fn csrf_token(&self, wiki: &mut Wiki) -> String {
+        if self.user(wiki).is_none() {
+            return "+\\".to_owned();
+        }
+        let next = wiki.next_id();
+        let session = wiki.sessions.entry(self.session.clone()).or_default();
+        if session.csrf_token.is_empty() {
+            session.csrf_token = format!("{next:032x}+\\");
+        }
+        session.csrf_token.clone()
+    }
+
+    fn login_token(&self, wiki: &mut Wiki) -> String {
+        let next = wiki.next_id();
+        let session = wiki.sessions.entry(self.session.clone()).or_default();
+        if session.login_token.is_empty() {
+            session.login_token = format!("{next:032x}+\\");
+        }
+        session.login_token.clone()
+    }
+
+    fn check_csrf(&self, wiki: &mut Wiki, params: &Params) -> Result<()> {
+        let token = params.require("token")?;
+        if wiki.rejected_tokens > 0 {
+            wiki.rejected_tokens -= 1;
+            if let Some(session) = wiki.sessions.get_mut(&self.session) {
+                session.csrf_token.clear();
+            }
+            return Err(ApiError::new("badtoken", "Invalid CSRF token."));
+        }
+        if token != self.csrf_token(wiki) {
+            return Err(ApiError::new("badtoken", "Invalid CSRF token."));
+        }
+        Ok(())
+    }
+}
+
+/// Answer a request. Errors are turned into an error response.
+pub fn handle(wiki: &mut Wiki, caller: &Caller, params: &Params, post: bool) -> Value {
+    let mut v = match dispatch(wiki, caller, params, post) {
+        Ok(v) => v,
+        Err(e) => e.into_value(),
+    };
+    if params.flag("curtimestamp") {
+        v["curtimestamp"] = timestamp(wiki.now()).into();
+    }
+    if params.flag("servedby") {
+        v["servedby"] = "fakewiki".into();
+    }
+    if let Some(id) = params.get("requestid") {
+        v["requestid"] = id.into();
+    }
+    v
+}
+
+fn dispatch(wiki: &mut Wiki, caller: &Caller, params: &Params, post: bool) -> Result<Value> {
+    check_format(params)?;
+    check_assert(wiki, caller, params)?;
+    let action = params.require("action")?;
+    let must_post = matches!(action, "edit" | "login" | "clientlogin");
+    if must_post && !post {
+        return Err(ApiError::new(
+            "mustbeposted",
+            format!("The \"{action}\" module requires a POST request."),
+        ));
+    }
+    match action {
+        "query" => query(wiki, caller, params),
+        "edit" => edit(wiki, caller, params),
+        "login" => login(wiki, caller, params),
+        "clientlogin" => client_login(wiki, caller, params),
+        "parse" => parse(wiki, params),
+        "sitematrix" => Ok(site_matrix(wiki)),
+        _ => Err(ApiError::bad_value("action", action)),
+    }
+}
+
+/// The wikis added with `FakeWiki::add_to_farm`. Wikis named like `enwiki` are grouped by
+/// language, the others are special wikis. The list is never continued.
+fn site_matrix(wiki: &Wiki) -> Value {
+    let mut languages: Vec<(&str, Vec<Value>)> = Vec::new();
+    let mut specials = Vec::new();
+    for (dbname, server) in &wiki.farm {
+        let lang = dbname
+            .strip_suffix("wiki")
+            .filter(|l| !l.is_empty() && l.bytes().all(|b| b.is_ascii_lowercase()));
+        match lang {
+            Some(lang) => {
+                let site = json!({
+                    "url": server, "dbname": dbname, "code": "wiki", "sitename": "Wikipedia",
+                });
+                match languages.iter_mut().find(|(l, _)| *l == lang) {
+                    Some((_, sites)) => sites.push(site),
+                    None => languages.push((lang, vec![site])),
+                }
+            }
+            None => specials.push(json!({
+                "url": server,
+                "dbname": dbname,
+                "code": dbname,
+                "lang": dbname,
+                "sitename": dbname,
+            })),
+        }
+    }
+    let mut matrix = Map::new();
+    matrix.insert("count".into(), wiki.farm.len().into());
+    for (i, (lang, site)) in languages.into_iter().enumerate() {
+        let group = json!({ "code": lang, "name": lang, "site": site, "dir": "ltr" });
+        matrix.insert(i.to_string(), group);
+    }
+    matrix.insert("specials".into(), specials.into());
+    json!({ "sitematrix": matrix })
+}
+
+fn check_format(params: &Params) -> Result<()> {
+    match params.get("format") {
+        Some("json") => {}
+        Some(v) => return Err(ApiError::bad_value("format", v)),
+        None => return Err(ApiError::missing("format")),
+    }
+    if params.get("formatversion") != Some("2") {
+        return Err(ApiError::new(
+            "badvalue",
+            "Only formatversion=2 is supported.",
+        ));
+    }
+    Ok(())
+}
+
+fn check_assert(wiki: &Wiki, caller: &Caller, params: &Params) -> Result<()> {
+    let user = caller.user(wiki);
+    match params.get("assert") {
+        None => {}
+        Some("anon") if user.is_some() => {
+            return Err(ApiError::new(
+                "assertanonfailed",
+                "You are logged in, but \"anon\" was asserted.",
+            ))
+        }
+        Some("user") if user.is_none() => {
+            return Err(ApiError::new(
+                "assertuserfailed",
+                "You are no longer logged in, so the action could not be completed.",
+            ))
+        }
+        Some("bot") if !user.as_deref().is_some_and(|u| wiki.has_right(u, "bot")) => {
+            return Err(ApiError::new(
+                "assertbotfailed",
+                "You do not have the \"bot\" right, so the action could not be completed.",
+            ))
+        }
+        Some("anon" | "user" | "bot") => {}
+        Some(v) => return Err(ApiError::bad_value("assert", v)),
+    }
+    if let Some(name) = params.get("assertuser") {
+        if user.as_deref() != Some(name) {
+            return Err(ApiError::new(
+                "assertnameduserfailed",
+                format!("You are no longer logged in as \"{name}\"."),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn timestamp(t: DateTime<Utc>) -> String {
+    t.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn query(wiki: &mut Wiki, caller: &Caller, params: &Params) -> Result<Value> {
+    let mut query = Map::new();
+    let mut cont = Map::new();
+
+    for meta in params.list("meta") {
+        match meta {
+            "tokens" => {
+                query.insert("tokens".into(), tokens(wiki, caller, params)?);
+            }
+            "siteinfo" => {
+                for prop in params.list("siprop") {
+                    let v = match prop {
+                        "general" => json!({
+                            "mainpage": "Main Page",
+                            "base": format!("{}/wiki/Main_Page", wiki.server),
+                            "sitename": "Fake Wiki",
+                            "wikiid": "fakewiki",
+                            "lang": "en",
+                            "case": "first-letter",
+                            "server": wiki.server,
+                            "servername": wiki.server.split("//").nth(1),
+                            "articlepath": "/wiki/$1",
+                            "scriptpath": "/w",
+                            "script": "/w/index.php",
+                            "generator": "MediaWiki 1.43.0",
+                        }),
+                        "namespaces" => {
+                            let mut out = Map::new();
+                            let special = [(-2, "Media"), (-1, "Special"), (0, "")];
+                            for (id, name) in special.into_iter().chain(NAMESPACES.iter().copied())
+                            {
+                                let mut ns = json!({
+                                    "id": id,
+                                    "case": "first-letter",
+                                    "name": name,
+                                    "subpages": id > 0 && id % 2 == 1,
+                                    "content": id == 0,
+                                });
+                                if id != 0 {
+                                    ns["canonical"] = name.into();
+                                }
+                                out.insert(id.to_string(), ns);
+                            }
+                            Value::Object(out)
+                        }
+                        "namespacealiases" => ALIASES
+                            .iter()
+                            .map(|&(alias, id)| json!({ "id": id, "alias": alias }))
+                            .collect(),
+                        "interwikimap" => json!([
+                            { "prefix": "wikipedia", "url": "https://en.wikipedia.org/wiki/$1" },
+                        ]),
+                        "extensions" => json!([]),
+                        "magicwords" => json!([
+                            { "name": "redirect", "aliases": ["#REDIRECT"], "case-sensitive": false },
+                        ]),
+                        _ => return Err(ApiError::bad_value("siprop", prop)),
+                    };
+                    query.insert(prop.into(), v);
+                }
+            }
+            "userinfo" => {
+                let name = caller.name(wiki);
+                let mut info = json!({ "id": wiki.user_id(&name), "name": name });
+                if caller.user(wiki).is_none() {
+                    info["anon"] = true.into();
+                }
+                if params.has("uiprop", "rights") {
+                    let rights = wiki.users.get(&name).map(|u| u.rights.clone());
+                    info["rights"] = json!(rights.unwrap_or_default());
+                }
+                query.insert("userinfo".into(), info);
+            }
+            _ => return Err(ApiError::bad_value("meta", meta)),
+        }
+    }
+
+    for list in params.list("list") {
+        let (key, values) = match list {
+            "categorymembers" => (
+                "categorymembers",
+                category_members(wiki, params, &mut cont)?,
+            ),
+            "recentchanges" => ("recentchanges", recent_changes(wiki, params, &mut cont)?),
+            "abuselog" => ("abuselog", abuse_log(wiki, params, &mut cont)?),
+            _ => return Err(ApiError::bad_value("list", list)),
+        };
+        query.insert(key.into(), Value::Array(values));
+    }
+
+    let titles = params.list("titles");
+    let page_ids = params.list("pageids");
+    if !titles.is_empty() || !page_ids.is_empty() {
+        let mut pages = Vec::new();
+        for t in titles {
+            let title = Title::parse(t)
+                .ok_or_else(|| ApiError::new("invalidtitle", format!("Bad title \"{t}\".")))?;
+            pages.push(match wiki.pages.get(&title) {
+                Some(page) => page_info(wiki, page, params, &mut cont)?,
+                None => json!({ "ns": title.ns, "title": title.text, "missing": true }),
+            });
+        }
+        for id in page_ids {
+            let id: u64 = id.parse().map_err(|_| ApiError::bad_value("pageids", id))?;
+            pages.push(match wiki.page_by_id(id) {
+                Some(page) => page_info(wiki, page, params, &mut cont)?,
+                None => json!({ "pageid": id, "missing": true }),
+            });
+        }
+        query.insert("pages".into(), Value::Array(pages));
+    } else if !params.list("prop").is_empty() {
+        return Err(ApiError::new(
+            "missingparam",
+            "The \"titles\" or \"pageids\" parameter must be set.",
+        ));
+    }
+
+    let mut res = json!({ "query": query });
+    if cont.is_empty() {
+        res["batchcomplete"] = true.into();
+    } else {
+        cont.insert("continue".into(), "-||".into());
+        res["continue"] = Value::Object(cont);
+    }
+    Ok(res)
+}
+
+fn tokens(wiki: &mut Wiki, caller: &Caller, params: &Params) -> Result<Value> {
+    let types = match params.list("type") {
+        t if t.is_empty() => vec!["csrf"],
+        t => t,
+    };
+    wiki.token_requests += 1;
+    let mut tokens = Map::new();
+    for ty in types {
+        let token = match ty {
+            "login" => caller.login_token(wiki),
+            "csrf" | "watch" | "patrol" | "rollback" | "userrights" | "createaccount" => {
+                caller.csrf_token(wiki)
+            }
+            _ => return Err(ApiError::bad_value("type", ty)),
+        };
+        tokens.insert(format!("{ty}token"), token.into());
+    }
+    Ok(Value::Object(tokens))
+}
+
+/// A page with the properties requested by `prop`.
+fn page_info(
+    wiki: &Wiki,
+    page: &Page,
+    params: &Params,
+    cont: &mut Map<String, Value>,
+) -> Result<Value> {
+    let mut v = json!({ "pageid": page.id, "ns": page.title.ns, "title": page.title.text });
+    for prop in params.list("prop") {
+        match prop {
+            "revisions" => v["revisions"] = revisions(wiki, page, params, cont)?,
+            _ => return Err(ApiError::bad_value("prop", prop)),
+        }
+    }
+    Ok(v)
+}
+
+/// The revisions of a page, newest first, continuing with `rvcontinue`.
+fn revisions(
+    wiki: &Wiki,
+    page: &Page,
+    params: &Params,
+    cont: &mut Map<String, Value>,
+) -> Result<Value> {
+    let limit = match params.get("rvlimit") {
+        Some(_) => params.limit("rvlimit")?,
+        None => 1,
+    };
+    let start = params
+        .get("rvcontinue")
+        .and_then(|c| c.rsplit('|').next()?.parse::<u64>().ok())
+        .unwrap_or(u64::MAX);
+    let mut ids = page.revisions.iter().rev().filter(|&&id| id <= start);
+    let out: Vec<_> = ids
+        .by_ref()
+        .take(limit)
+        .map(|id| revision(&wiki.revisions[id], params))
+        .collect();
+    if let Some(next) = ids.next() {
+        let next = &wiki.revisions[next];
+        cont.insert(
+            "rvcontinue".into(),
+            format!("{}|{}", next.timestamp.format("%Y%m%d%H%M%S"), next.id).into(),
+        );
+    }
+    Ok(Value::Array(out))
+}
+
+fn revision(rev: &Revision, params: &Params) -> Value {
+    let props = match params.list("rvprop") {
+        p if p.is_empty() => vec!["ids", "timestamp", "flags", "comment", "user"],
+        p => p,
+    };
+    let mut v = Map::new();
+    for prop in props {
+        match prop {
+            "ids" => {
+                v.insert("revid".into(), rev.id.into());
+                v.insert("parentid".into(), rev.parent_id.into());
+            }
+            "timestamp" => {
+                v.insert("timestamp".into(), timestamp(rev.timestamp).into());
+            }
+            "flags" => {
+                v.insert("minor".into(), rev.minor.into());
+            }
+            "comment" => {
+                v.insert("comment".into(), rev.comment.clone().into());
+            }
+            "user" => {
+                v.insert("user".into(), rev.user.clone().into());
+            }
+            "size" => {
+                v.insert("size".into(), rev.text.len().into());
+            }
+            "content" => {
+                let slot = json!({
+                    "contentmodel": "wikitext",
+                    "contentformat": "text/x-wiki",
+                    "content": rev.text,
+                });
+                v.insert("slots".into(), json!({ "main": slot }));
+            }
+            _ => {}
+        }
+    }
+    Value::Object(v)
+}
+
+fn category_members(
+    wiki: &Wiki,
+    params: &Params,
+    cont: &mut Map<String, Value>,
+) -> Result<Vec<Value>> {
+    let category = match (params.title("cmtitle")?, params.int("cmpageid")?) {
+        (Some(title), None) => title,
+        (None, Some(id)) => wiki
+            .page_by_id(id)
+            .map(|p| p.title.clone())
+            .ok_or_else(|| {
+                ApiError::new("nosuchpageid", format!("There is no page with ID {id}."))
+            })?,
+        (Some(_), Some(_)) => {
+            return Err(ApiError::new(
+                "invalidparammix",
+                "The parameters \"cmtitle\" and \"cmpageid\" can not be used together.",
+            ))
+        }
+        (None, None) => {
+            return Err(ApiError::new(
+                "invalidparammix",
+                "One of the parameters \"cmtitle\" and \"cmpageid\" is required.",
+            ))
+        }
+    };
+    if category.ns != NS_CATEGORY {
+        return Err(ApiError::new(
+            "invalidcategory",
+            "The category name you entered is not valid.",
+        ));
+    }
+    let types = match params.list("cmtype") {
+        t if t.is_empty() => vec!["page", "subcat", "file"],
+        t => t,
+    };
+    let props = match params.list("cmprop") {
+        p if p.is_empty() => vec!["ids", "title"],
+        p => p,
+    };
+    let namespaces = params.namespaces("cmnamespace")?;
+    let limit = params.limit("cmlimit")?;
+    let from = params.get("cmcontinue").unwrap_or_default();
+
+    let mut members = wiki
+        .category_members(&category)
+        .into_iter()
+        .map(|p| {
+            let ty = match p.title.ns {
+                NS_CATEGORY => "subcat",
+                NS_FILE => "file",
+                _ => "page",
+            };
+            (p, ty)
+        })
+        .filter(|(p, ty)| {
+            types.contains(ty)
+                && (namespaces.is_empty() || namespaces.contains(&p.title.ns))
+                && p.title.text.as_str() >= from
+        });
+    let mut out = Vec::new();
+    for (page, ty) in members.by_ref().take(limit) {
+        let mut v = Map::new();
+        for prop in &props {
+            match *prop {
+                "ids" => {
+                    v.insert("pageid".into(), page.id.into());
+                }
+                "title" => {
+                    v.insert("ns".into(), page.title.ns.into());
+                    v.insert("title".into(), page.title.text.clone().into());
+                }
+                "type" => {
+                    v.insert("type".into(), ty.into());
+                }
+                "timestamp" => {
+                    let added = wiki.revisions[&page.revisions[0]].timestamp;
+                    v.insert("timestamp".into(), timestamp(added).into());
+                }
+                _ => {}
+            }
+        }
+        out.push(Value::Object(v));
+    }
+    if let Some((next, _)) = members.next() {
+        cont.insert("cmcontinue".into(), next.title.text.clone().into());
+    }
+    Ok(out)
+}
+
+/// Recent changes, newest first, continuing with `rccontinue`.
+fn recent_changes(
+    wiki: &Wiki,
+    params: &Params,
+    cont: &mut Map<String, Value>,
+) -> Result<Vec<Value>> {
+    let start = params.timestamp(wiki, "rcstart")?;
+    let end = params.timestamp(wiki, "rcend")?;
+    let types = params.list("rctype");
+    let props = match params.list("rcprop") {
+        p if p.is_empty() => vec!["title", "timestamp", "ids"],
+        p => p,
+    };
+    let namespaces = params.namespaces("rcnamespace")?;
+    let limit = params.limit("rclimit")?;
+    let from = params
+        .get("rccontinue")
+        .and_then(|c| c.rsplit('|').next()?.parse::<u64>().ok())
+        .unwrap_or(u64::MAX);
+
+    let mut changes = wiki.recent_changes.iter().rev().filter(|rc| {
+        rc.id <= from
+            && start.is_none_or(|s| rc.timestamp <= s)
+            && end.is_none_or(|e| rc.timestamp >= e)
+            && (types.is_empty() || types.contains(&rc.ty))
+            && (namespaces.is_empty() || namespaces.contains(&rc.title.ns))
+    });
+    let mut out = Vec::new();
+    for rc in changes.by_ref().take(limit) {
+        let mut v = json!({ "type": rc.ty });
+        for prop in &props {
+            match *prop {
+                "title" => {
+                    v["ns"] = rc.title.ns.into();
+                    v["title"] = rc.title.text.clone().into();
+                }
+                "ids" => {
+                    v["rcid"] = rc.id.into();
+                    v["pageid"] = rc.page_id.into();
+                    v["revid"] = rc.rev_id.into();
+                    v["old_revid"] = rc.old_rev_id.into();
+                }
+                "user" => v["user"] = rc.user.clone().into(),
+                "userid" => v["userid"] = rc.user_id.into(),
+                "timestamp" => v["timestamp"] = timestamp(rc.timestamp).into(),
+                "comment" => v["comment"] = rc.comment.clone().into(),
+                "sizes" => {
+                    v["oldlen"] = rc.old_len.into();
+                    v["newlen"] = rc.new_len.into();
+                }
+                "flags" => {
+                    v["new"] = (rc.ty == "new").into();
+                    v["minor"] = rc.minor.into();
+                    v["bot"] = rc.bot.into();
+                }
+                _ => {}
+            }
+        }
+        out.push(v);
+    }
+    if let Some(next) = changes.next() {
+        cont.insert(
+            "rccontinue".into(),
+            format!("{}|{}", next.timestamp.format("%Y%m%d%H%M%S"), next.id).into(),
+        );
+    }
+    Ok(out)
+}
+
+/// Abuse log entries, newest first, continuing with `aflstart`.
+fn abuse_log(wiki: &Wiki, params: &Params, cont: &mut Map<String, Value>) -> Result<Vec<Value>> {
+    let start = params.timestamp(wiki, "aflstart")?;
+    let end = params.timestamp(wiki, "aflend")?;
+    let filters = params.list("aflfilter");
+    let log_id = params.int("afllogid")?;
+    let props = match params.list("aflprop") {
+        p if p.is_empty() => vec![
+            "ids",
+            "user",
+            "title",
+            "action",
+            "result",
+            "timestamp",
+            "hidden",
+            "revid",
+        ],
+        p => p,
+    };
+    let limit = params.limit("afllimit")?;
+
+    let mut entries = wiki.abuse_log.iter().rev().filter(|e| {
+        start.is_none_or(|s| e.timestamp <= s)
+            && end.is_none_or(|t| e.timestamp >= t)
+            && log_id.is_none_or(|id| e.id == id)
+            && (filters.is_empty() || filters.contains(&e.filter_id.to_string().as_str()))
+    });
+    let mut out = Vec::new();
+    for e in entries.by_ref().take(limit) {
+        let mut v = Map::new();
+        for prop in &props {
+            match *prop {
+                "ids" => {
+                    v.insert("id".into(), e.id.into());
+                    v.insert("filter_id".into(), e.filter_id.to_string().into());
+                }
+                "filter" => {
+                    v.insert("filter".into(), format!("Filter {}", e.filter_id).into());
+                }
+                "user" => {
+                    v.insert("user".into(), e.user.clone().into());
+                }
+                "title" => {
+                    v.insert("ns".into(), e.title.ns.into());
+                    v.insert("title".into(), e.title.text.clone().into());
+                }
+                "action" => {
+                    v.insert("action".into(), e.action.clone().into());
+                }
+                "result" => {
+                    v.insert("result".into(), e.result.clone().into());
+                }
+                "timestamp" => {
+                    v.insert("timestamp".into(), timestamp(e.timestamp).into());
+                }
+                "hidden" => {
+                    v.insert("hidden".into(), false.into());
+                }
+                "revid" => {
+                    if let Some(id) = e.rev_id {
+                        v.insert("revid".into(), id.into());
+                    }
+                }
+                _ => {}
+            }
+        }
+        out.push(Value::Object(v));
+    }
+    if let Some(next) = entries.next() {
+        cont.insert("aflstart".into(), timestamp(next.timestamp).into());
+    }
+    Ok(out)
+}
+
+fn edit(wiki: &mut Wiki, caller: &Caller, params: &Params) -> Result<Value> {
+    caller.check_csrf(wiki, params)?;
+    let title = match (params.title("title")?, params.int("pageid")?) {
+        (Some(title), None) => title,
+        (None, Some(id)) => wiki
+            .page_by_id(id)
+            .map(|p| p.title.clone())
+            .ok_or_else(|| {
+                ApiError::new("nosuchpageid", format!("There is no page with ID {id}."))
+            })?,
+        _ => {
+            return Err(ApiError::new(
+                "invalidparammix",
+                "Exactly one of the parameters \"title\" and \"pageid\" is required.",
+            ))
+        }
+    };
+    let current = wiki.pages.get(&title).map(|p| wiki.latest(p).text.clone());
+
+    let text = match (params.get("text"), params.get("section")) {
+        (Some(text), Some("new")) => {
+            let heading = params
+                .get("sectiontitle")
+                .or(params.get("summary"))
+                .unwrap_or_default();
+            let mut new = current.clone().unwrap_or_default();
+            if !new.is_empty() {
+                new.push_str("\n\n");
+            }
+            new.push_str(&format!("== {heading} ==\n\n{text}"));
+            new
+        }
+        (_, Some(section)) => return Err(ApiError::bad_value("section", section)),
+        (Some(text), None) => text.to_owned(),
+        (None, None) => {
+            let (prepend, append) = (params.get("prependtext"), params.get("appendtext"));
+            if prepend.is_none() && append.is_none() {
+                return Err(ApiError::new(
+                    "missingparam",
+                    "One of the parameters \"text\", \"appendtext\" and \"prependtext\" is required.",
+                ));
+            }
+            format!(
+                "{}{}{}",
+                prepend.unwrap_or_default(),
+                current.as_deref().unwrap_or_default(),
+                append.unwrap_or_default()
+            )
+        }
+    };
+
+    let base = match (
+        params.int("baserevid")?,
+        params.timestamp(wiki, "basetimestamp")?,
+    ) {
+        (Some(id), _) => Some(Base::RevId(id)),
+        (None, Some(t)) => Some(Base::Timestamp(t)),
+        (None, None) => None,
+    };
+    let create = if params.flag("createonly") {
+        Create::Only
+    } else if params.flag("nocreate") {
+        Create::Never
+    } else {
+        Create::Allow
+    };
+    let user = caller.name(wiki);
+    let bot = params.flag("bot") && wiki.has_right(&user, "bot");
+    let summary = params.get("summary").unwrap_or_default();
+    let minor = params.flag("minor");
+
+    let outcome = wiki
+        .edit(&title, text, &user, summary, minor, bot, base, create)
+        .map_err(|e| match e {
+            EditError::Conflict => ApiError::new("editconflict", "Edit conflict."),
+            EditError::ArticleExists => ApiError::new(
+                "articleexists",
+                "The article you tried to create has been created already.",
+            ),
+            EditError::MissingTitle => {
+                ApiError::new("missingtitle", "The page you specified doesn't exist.")
+            }
+        })?;
+
+    let mut res = json!({
+        "result": "Success",
+        "pageid": outcome.page_id,
+        "title": outcome.title.text,
+        "contentmodel": "wikitext",
+    });
+    match outcome.new_rev_id {
+        Some(id) => {
+            if outcome.old_rev_id == 0 {
+                res["new"] = true.into();
+            }
+            res["oldrevid"] = outcome.old_rev_id.into();
+            res["newrevid"] = id.into();
+            res["newtimestamp"] = timestamp(outcome.timestamp).into();
+        }
+        None => res["nochange"] = true.into(),
+    }
+    Ok(json!({ "edit": res }))
+}
+
+fn login(wiki: &mut Wiki, caller: &Caller, params: &Params) -> Result<Value> {
+    let token = params.get("lgtoken").unwrap_or_default();
+    let expected = wiki
+        .sessions
+        .get(&caller.session)
+        .map(|s| s.login_token.clone())
+        .unwrap_or_default();
+    if token.is_empty() || expected.is_empty() {
+        return Ok(
+            json!({ "login": { "result": "NeedToken", "token": caller.login_token(wiki) } }),
+        );
+    }
+    if token != expected {
+        return Ok(json!({ "login": { "result": "WrongToken" } }));
+    }
+    let name = params.get("lgname").unwrap_or_default();
+    let password = params.get("lgpassword").unwrap_or_default();
+    match authenticate(wiki, caller, name, password) {
+        Some((id, name)) => Ok(json!({
+            "login": { "result": "Success", "lguserid": id, "lgusername": name }
+        })),
+        None => Ok(json!({
+            "login": {
+                "result": "Failed",
+                "reason": "Incorrect username or password entered. Please try again.",
+            }
+        })),
+    }
+}
+
+fn client_login(wiki: &mut Wiki, caller: &Caller, params: &Params) -> Result<Value> {
+    let token = params.require("logintoken")?;
+    let expected = wiki.sessions.get(&caller.session).map(|s| &s.login_token);
+    if expected.map(String::as_str) != Some(token) {
+        return Err(ApiError::new("badtoken", "Invalid login token."));
+    }
+    let name = params.get("username").unwrap_or_default();
+    let password = params.get("password").unwrap_or_default();
+    match authenticate(wiki, caller, name, password) {
+        Some((_, name)) => Ok(json!({ "clientlogin": { "status": "PASS", "username": name } })),
+        None => Ok(json!({
+            "clientlogin": {
+                "status": "FAIL",
+                "message": "Incorrect username or password entered. Please try again.",
+                "messagecode": "wrongpassword",
+            }
+        })),
+    }
+}
+
+/// Log the session of the caller in if the password matches. A new session gets new tokens.
+fn authenticate(
+    wiki: &mut Wiki,
+    caller: &Caller,
+    name: &str,
+    password: &str,
+) -> Option<(u64, String)> {
+    let title = Title::parse(name)?;
+    let user = wiki
+        .users
+        .get(&title.text)
+        .filter(|u| u.password == password)?;
+    let (id, name) = (user.id, user.name.clone());
+    let session = wiki.sessions.entry(caller.session.clone()).or_default();
+    session.user = Some(name.clone());
+    session.login_token.clear();
+    session.csrf_token.clear();
+    Some((id, name))
+}
+
+fn parse(wiki: &Wiki, params: &Params) -> Result<Value> {
+    let (title, rev) = if let Some(id) = params.int("oldid")? {
+        let rev = wiki.revisions.get(&id).ok_or_else(|| {
+            ApiError::new("nosuchrevid", format!("There is no revision with ID {id}."))
+        })?;
+        let page = wiki
+            .page_by_id(rev.page_id)
+            .expect("revisions belong to pages");
+        (page.title.clone(), Some(rev))
+    } else if let Some(page) = params.title("page")? {
+        let p = wiki.pages.get(&page).ok_or_else(|| {
+            ApiError::new("missingtitle", "The page you specified doesn't exist.")
+        })?;
+        (page, Some(wiki.latest(p)))
+    } else if let Some(id) = params.int("pageid")? {
+        let p = wiki.page_by_id(id).ok_or_else(|| {
+            ApiError::new("nosuchpageid", format!("There is no page with ID {id}."))
+        })?;
+        (p.title.clone(), Some(wiki.latest(p)))
+    } else {
+        let title = params
+            .title("title")?
+            .unwrap_or_else(|| Title::parse("API").unwrap());
+        (title, None)
+    };
+    let text = match rev {
+        Some(rev) => rev.text.as_str(),
+        None => params.get("text").unwrap_or_default(),
+    };
+
+    let props = match params.list("prop") {
+        p if p.is_empty() => vec!["links", "categories", "revid"],
+        p => p,
+    };
+    let mut v = json!({ "title": title.text });
+    if let Some(rev) = rev {
+        v["pageid"] = rev.page_id.into();
+    }
+    let links = links(text);
+    for prop in props {
+        match prop {
+            "links" => {
+                let out: Vec<_> = links
+                    .iter()
+                    .filter(|l| !l.category)
+                    .map(|l| {
+                        json!({
+                            "ns": l.title.ns,
+                            "title": l.title.text,
+                            "exists": wiki.pages.contains_key(&l.title),
+                        })
+                    })
+                    .collect();
+                v["links"] = out.into();
+            }
+            "categories" => {
+                let out: Vec<_> = links
+                    .iter()
+                    .filter(|l| l.category)
+                    .map(|l| {
+                        let name = l.title.text.split_once(':').map_or("", |(_, n)| n);
+                        json!({ "sortkey": "", "category": name.replace(' ', "_") })
+                    })
+                    .collect();
+                v["categories"] = out.into();
+            }
+            "wikitext" => v["wikitext"] = text.into(),
+            "revid" => v["revid"] = rev.map_or(0, |r| r.id).into(),
+            _ => {}
+        }
+    }
+    Ok(json!({ "parse": v }))
+}
@@ -0,0 +1,347 @@
+//! An in-memory stand-in for the MediaWiki action API, for testing tools without touching a real
+//! wiki.
+//!
+//! [`FakeWiki`] holds pages, users and logs, and [`FakeWiki::serve`] answers `api.php` requests
+//! for them on a local port. It covers `query` (`revisions`, `categorymembers`, `recentchanges`,
+//! `abuselog`, `tokens`, `userinfo` and `siteinfo`, with continuation), `edit` (with tokens and conflicts),
+//! `login`, `clientlogin`, `parse` and `sitematrix`.
+//!
+//! ```no_run
+//! # async fn f() -> wiki::Result<()> {
+//! let fake = fakewiki::FakeWiki::new();
+//! fake.add_user("Example", "hunter2");
+//! fake.edit("Main Page", "Hello, [[world]]!");
+//! let server = fake.serve().await?;
+//!
+//! let client = wiki::ClientBuilder::new(&server.api_url())
+//!     .password(wiki::BotPassword::new("Example", "hunter2"))
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::body::Bytes;
+use axum::extract::{FromRequest, Multipart, Request};
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, RETRY_AFTER, SET_COOKIE};
+use axum::http::{HeaderMap, HeaderValue, Method};
+use axum::response::{IntoResponse, Response};
+use axum::routing::any;
+use axum::{Json, Router};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+pub use crate::wiki::{Revision, Title};
+
+mod api;
+mod wiki;
+
+use crate::api::{ApiError, Caller, Params};
+use crate::wiki::{Create, User, Wiki};
+
+const SESSION_COOKIE: &str = "fakewiki_session";
+
+/// A wiki kept in memory. Clones share the same state, so a test can keep one to set up pages
+/// and inspect edits while a [`Server`] answers requests.
+#[derive(Clone, Default)]
+pub struct FakeWiki {
+    wiki: Arc<Mutex<Wiki>>,
+}
+
+impl FakeWiki {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Wiki> {
+        self.wiki.lock().unwrap()
+    }
+
+    /// Create a user that can log in with `password`. Returns the user id.
+    pub fn add_user(&self, name: &str, password: &str) -> u64 {
+        self.add_user_with_rights(name, password, &[])
+    }
+
+    /// Create a user with the `bot` right.
+    pub fn add_bot(&self, name: &str, password: &str) -> u64 {
+        self.add_user_with_rights(name, password, &["bot"])
+    }
+
+    fn add_user_with_rights(&self, name: &str, password: &str, rights: &[&str]) -> u64 {
+        let mut wiki = self.lock();
+        let id = wiki.next_id();
+        let rights = ["read", "edit"].iter().chain(rights);
+        wiki.users.insert(
+            name.to_owned(),
+            User {
+                id,
+                name: name.to_owned(),
+                password: password.to_owned(),
+                rights: rights.map(|&r| r.to_owned()).collect(),
+            },
+        );
+        id
+    }
+
+    /// Accept `token` as an OAuth 2 access token of `user`, as with an owner-only client.
+    pub fn add_oauth_token(&self, token: &str, user: &str) {
+        self.lock()
+            .oauth_tokens
+            .insert(token.to_owned(), user.to_owned());
+    }
+
+    /// Log every session out, as if their cookies had expired.
+    pub fn expire_sessions(&self) {
+        for session in self.lock().sessions.values_mut() {
+            session.user = None;
+        }
+    }
+
+    /// Save a revision of a page as `MediaWiki default`. Returns the revision id.
+    pub fn edit(&self, title: &str, text: &str) -> u64 {
+        self.edit_as(title, text, "MediaWiki default", "")
+    }
+
+    /// Save a revision of a page as `user`. Returns the revision id, or the id of the current
+    /// revision if the text did not change.
+    pub fn edit_as(&self, title: &str, text: &str, user: &str, summary: &str) -> u64 {
+        let title = Title::parse(title).expect("valid title");
+        let mut wiki = self.lock();
+        let outcome = wiki
+            .edit(
+                &title,
+                text.to_owned(),
+                user,
+                summary,
+                false,
+                false,
+                None,
+                Create::Allow,
+            )
+            .expect("edits without a base do not conflict");
+        outcome.new_rev_id.unwrap_or(outcome.old_rev_id)
+    }
+
+    /// The current text of a page.
+    pub fn text(&self, title: &str) -> Option<String> {
+        let title = Title::parse(title)?;
+        let wiki = self.lock();
+        let page = wiki.pages.get(&title)?;
+        Some(wiki.latest(page).text.clone())
+    }
+
+    /// The revisions of a page, oldest first.
+    pub fn history(&self, title: &str) -> Vec<Revision> {
+        let Some(title) = Title::parse(title) else {
+            return Vec::new();
+        };
+        let wiki = self.lock();
+        let Some(page) = wiki.pages.get(&title) else {
+            return Vec::new();
+        };
+        page.revisions
+            .iter()
+            .map(|id| wiki.revisions[id].clone())
+            .collect()
+    }
+
+    /// Record a hit of an abuse filter. Returns the log id.
+    pub fn log_abuse(&self, filter_id: u64, user: &str, title: &str, action: &str) -> u64 {
+        let title = Title::parse(title).expect("valid title");
+        self.lock().log_abuse(filter_id, user, title, action, "")
+    }
+
+    /// How many `meta=tokens` requests were answered so far.
+    pub fn token_requests(&self) -> u32 {
+        self.lock().token_requests
+    }
+
+    /// Refuse the CSRF tokens of the next `n` writes with `badtoken`, handing out a new token
+    /// each time, as when a session is renewed.
+    pub fn reject_tokens(&self, n: u32) {
+        self.lock().rejected_tokens = n;
+    }
+
+    /// Report this many seconds of replication lag, so that requests with a lower `maxlag` fail.
+    pub fn set_lag(&self, seconds: u32) {
+        self.lock().lag = seconds;
+    }
+
+    /// List a wiki in `action=sitematrix`, such as another fake wiki with the server
+    /// `http://{addr}`.
+    pub fn add_to_farm(&self, dbname: &str, server: &str) {
+        self.lock()
+            .farm
+            .push((dbname.to_owned(), server.to_owned()));
+    }
+
+    /// The router answering `/w/api.php`, to be served by the caller.
+    pub fn router(&self) -> Router {
+        let wiki = self.clone();
+        Router::new().route(
+            "/w/api.php",
+            any(move |req: Request| {
+                let wiki = wiki.clone();
+                async move { wiki.respond(req).await }
+            }),
+        )
+    }
+
+    /// Serve the API on a free port of the loopback interface until the [`Server`] is dropped.
+    pub async fn serve(&self) -> io::Result<Server> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        self.lock().server = format!("http://{addr}");
+        let router = self.router();
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                debug!("fake wiki stopped: {e}");
+            }
+        });
+        Ok(Server { addr, task })
+    }
+
+    async fn respond(&self, req: Request) -> Response {
+        let post = req.method() == Method::POST;
+        let (caller, new_session) = self.caller(req.headers());
+        let params = match read_params(req).await {
+            Ok(params) => params,
+            Err(e) => return Json(e.into_value()).into_response(),
+        };
+        debug!(?params, "request");
+
+        let mut res = match self.lagged(&params) {
+            Some(lag) => {
+                let e = ApiError::new(
+                    "maxlag",
+                    format!("Waiting for a database server: {lag} seconds lagged."),
+                );
+                let mut res = Json(e.into_value()).into_response();
+                res.headers_mut().insert(RETRY_AFTER, HeaderValue::from(5));
+                res
+            }
+            None => {
+                let v: Value = api::handle(&mut self.lock(), &caller, &params, post);
+                Json(v).into_response()
+            }
+        };
+        if new_session {
+            let cookie = format!("{SESSION_COOKIE}={}; Path=/; HttpOnly", caller.session);
+            res.headers_mut()
+                .insert(SET_COOKIE, HeaderValue::from_str(&cookie).unwrap());
+        }
+        res
+    }
+
+    /// The lag to report if it exceeds the `maxlag` of the request.
+    fn lagged(&self, params: &Params) -> Option<u32> {
+        let max: u32 = params.get("maxlag")?.parse().ok()?;
+        let lag = self.lock().lag;
+        (lag > max).then_some(lag)
+    }
+
+    /// Identify the caller by OAuth token or session cookie, starting a new session if needed.
+    fn caller(&self, headers: &HeaderMap) -> (Caller, bool) {
+        let bearer = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        if let Some(token) = bearer {
+            let user = self.lock().oauth_tokens.get(token).cloned();
+            let caller = Caller {
+                session: format!("oauth:{token}"),
+                oauth_user: user,
+            };
+            return (caller, false);
+        }
+        let cookie = headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(';'))
+            .find_map(|c| c.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='));
+        let mut wiki = self.lock();
+        match cookie.filter(|c| wiki.sessions.contains_key(*c)) {
+            Some(session) => (
+                Caller {
+                    session: session.to_owned(),
+                    oauth_user: None,
+                },
+                false,
+            ),
+            None => {
+                let session = format!("s{:016x}", wiki.next_id());
+                wiki.sessions.insert(session.clone(), Default::default());
+                let caller = Caller {
+                    session,
+                    oauth_user: None,
+                };
+                (caller, true)
+            }
+        }
+    }
+}
+
+/// Collect the parameters from the query string and, for POST requests, the body.
+async fn read_params(req: Request) -> Result<Params, ApiError> {
+    let mut params: Vec<(String, String)> = req
+        .uri()
+        .query()
+        .map(|q| {
+            url::form_urlencoded::parse(q.as_bytes())
+                .into_owned()
+                .collect()
+        })
+        .unwrap_or_default();
+    let multipart = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+    let bad_body = |e: &dyn std::fmt::Display| ApiError::new("badrequest", e.to_string());
+    if multipart {
+        let mut form = Multipart::from_request(req, &())
+            .await
+            .map_err(|e| bad_body(&e))?;
+        while let Some(field) = form.next_field().await.map_err(|e| bad_body(&e))? {
+            let name = field.name().unwrap_or_default().to_owned();
+            let value = field.text().await.map_err(|e| bad_body(&e))?;
+            params.push((name, value));
+        }
+    } else {
+        let body: Bytes = axum::body::to_bytes(req.into_body(), usize::MAX)
+            .await
+            .map_err(|e| bad_body(&e))?;
+        params.extend(url::form_urlencoded::parse(&body).into_owned());
+    }
+    Ok(params.into_iter().collect())
+}
+
+/// A running [`FakeWiki`], stopped when dropped.
+pub struct Server {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl Server {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The URL of `api.php`, to pass to `wiki::ClientBuilder::new`.
+    pub fn api_url(&self) -> String {
+        format!("http://{}/w/api.php", self.addr)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
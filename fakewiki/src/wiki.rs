@@ -0,0 +1,426 @@
+//! The in-memory state of a wiki: pages and their history, users, sessions and logs.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+/// Namespaces with a name, as on English Wikipedia.
+pub const NAMESPACES: &[(i64, &str)] = &[
+    (1, "Talk"),
+    (2, "User"),
+    (3, "User talk"),
+    (4, "Wikipedia"),
+    (5, "Wikipedia talk"),
+    (6, "File"),
+    (7, "File talk"),
+    (8, "MediaWiki"),
+    (10, "Template"),
+    (11, "Template talk"),
+    (12, "Help"),
+    (14, "Category"),
+    (15, "Category talk"),
+];
+
+/// Other names of namespaces.
+pub const ALIASES: &[(&str, i64)] = &[
+    ("WP", 4),
+    ("Project", 4),
+    ("Project talk", 5),
+    ("Image", 6),
+    ("Image talk", 7),
+];
+
+pub const NS_FILE: i64 = 6;
+pub const NS_CATEGORY: i64 = 14;
+
+/// A normalized title: underscores are spaces, the namespace is canonical and the first letter of
+/// the page name is uppercase.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Title {
+    pub ns: i64,
+    pub text: String,
+}
+
+impl Title {
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.replace('_', " ");
+        let s = s.trim().trim_start_matches(':').trim();
+        let (ns, name) = match s.split_once(':') {
+            Some((prefix, rest)) => match NAMESPACES
+                .iter()
+                .copied()
+                .chain(ALIASES.iter().map(|&(name, ns)| (ns, name)))
+                .find(|(_, name)| name.eq_ignore_ascii_case(prefix.trim()))
+            {
+                Some((ns, _)) => (ns, rest.trim()),
+                None => (0, s),
+            },
+            None => (0, s),
+        };
+        if name.is_empty() || name.contains(['[', ']', '{', '}', '|', '#', '<', '>']) {
+            return None;
+        }
+        let mut chars = name.chars();
+        let first = chars.next()?;
+        let text = match NAMESPACES.iter().find(|(n, _)| *n == ns) {
+            Some((_, prefix)) => format!("{prefix}:{}{}", first.to_uppercase(), chars.as_str()),
+            None => format!("{}{}", first.to_uppercase(), chars.as_str()),
+        };
+        Some(Self { ns, text })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Page {
+    pub id: u64,
+    pub title: Title,
+    /// Revision ids, oldest first.
+    pub revisions: Vec<u64>,
+}
+
+/// A revision of a page.
+#[derive(Clone, Debug)]
+pub struct Revision {
+    pub id: u64,
+    pub parent_id: u64,
+    pub page_id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub comment: String,
+    pub minor: bool,
+    pub text: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct User {
+    pub id: u64,
+    pub name: String,
+    pub password: String,
+    pub rights: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    pub user: Option<String>,
+    pub login_token: String,
+    pub csrf_token: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecentChange {
+    pub id: u64,
+    pub ty: &'static str,
+    pub title: Title,
+    pub page_id: u64,
+    pub rev_id: u64,
+    pub old_rev_id: u64,
+    pub user: String,
+    pub user_id: u64,
+    pub old_len: usize,
+    pub new_len: usize,
+    pub timestamp: DateTime<Utc>,
+    pub comment: String,
+    pub minor: bool,
+    pub bot: bool,
+}
+
+/// A hit of an abuse filter.
+#[derive(Clone, Debug)]
+pub struct AbuseLogEntry {
+    pub id: u64,
+    pub filter_id: u64,
+    pub user: String,
+    pub title: Title,
+    pub action: String,
+    pub result: String,
+    pub timestamp: DateTime<Utc>,
+    pub rev_id: Option<u64>,
+}
+
+/// Why an edit was refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditError {
+    Conflict,
+    ArticleExists,
+    MissingTitle,
+}
+
+pub struct EditOutcome {
+    pub page_id: u64,
+    pub title: Title,
+    pub old_rev_id: u64,
+    /// `None` if the text did not change.
+    pub new_rev_id: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Everything the wiki knows. Time only moves when something happens, so that timestamps are
+/// deterministic and distinct.
+pub struct Wiki {
+    clock: DateTime<Utc>,
+    next_id: u64,
+    pub pages: BTreeMap<Title, Page>,
+    pub revisions: BTreeMap<u64, Revision>,
+    pub users: HashMap<String, User>,
+    pub sessions: HashMap<String, Session>,
+    pub oauth_tokens: HashMap<String, String>,
+    pub recent_changes: Vec<RecentChange>,
+    pub abuse_log: Vec<AbuseLogEntry>,
+    /// The replication lag reported to requests with `maxlag`.
+    pub lag: u32,
+    /// Where the wiki is served, such as `http://127.0.0.1:8080`.
+    pub server: String,
+    /// The database names and servers listed by `action=sitematrix`.
+    pub farm: Vec<(String, String)>,
+    /// How many `meta=tokens` requests were answered.
+    pub token_requests: u32,
+    /// How many of the next CSRF tokens to refuse with `badtoken`, even if they are valid.
+    pub rejected_tokens: u32,
+}
+
+impl Default for Wiki {
+    fn default() -> Self {
+        Self {
+            clock: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            next_id: 1,
+            pages: BTreeMap::new(),
+            revisions: BTreeMap::new(),
+            users: HashMap::new(),
+            sessions: HashMap::new(),
+            oauth_tokens: HashMap::new(),
+            recent_changes: Vec::new(),
+            abuse_log: Vec::new(),
+            lag: 0,
+            server: "http://localhost".to_owned(),
+            farm: Vec::new(),
+            token_requests: 0,
+            rejected_tokens: 0,
+        }
+    }
+}
+
+impl Wiki {
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock
+    }
+
+    fn tick(&mut self) -> DateTime<Utc> {
+        self.clock += Duration::seconds(1);
+        self.clock
+    }
+
+    /// Ids are shared by all kinds of objects, which makes mixing them up in tests visible.
+    pub fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn page_by_id(&self, id: u64) -> Option<&Page> {
+        self.pages.values().find(|p| p.id == id)
+    }
+
+    pub fn latest(&self, page: &Page) -> &Revision {
+        &self.revisions[page.revisions.last().expect("pages have revisions")]
+    }
+
+    pub fn user_id(&self, name: &str) -> u64 {
+        self.users.get(name).map_or(0, |u| u.id)
+    }
+
+    pub fn has_right(&self, user: &str, right: &str) -> bool {
+        self.users
+            .get(user)
+            .is_some_and(|u| u.rights.iter().any(|r| r == right))
+    }
+
+    /// Save a new revision of a page.
+    #[allow(clippy::too_many_arguments)]
+    pub fn edit(
+        &mut self,
+        title: &Title,
+        text: String,
+        user: &str,
+        comment: &str,
+        minor: bool,
+        bot: bool,
+        base: Option<Base>,
+        create: Create,
+    ) -> Result<EditOutcome, EditError> {
+        let page = self.pages.get(title);
+        match (page, create) {
+            (Some(_), Create::Only) => return Err(EditError::ArticleExists),
+            (None, Create::Never) => return Err(EditError::MissingTitle),
+            _ => {}
+        }
+        if let (Some(page), Some(base)) = (page, base) {
+            let latest = self.latest(page);
+            let conflict = match base {
+                Base::RevId(id) => latest.id != id,
+                Base::Timestamp(t) => latest.timestamp > t,
+            };
+            if conflict && latest.user != user {
+                return Err(EditError::Conflict);
+            }
+        }
+        let (page_id, old) = match page {
+            Some(page) => {
+                let latest = self.latest(page);
+                (
+                    page.id,
+                    Some((latest.id, latest.text.len(), latest.text == text)),
+                )
+            }
+            None => (self.next_id(), None),
+        };
+        if let Some((old_id, _, true)) = old {
+            return Ok(EditOutcome {
+                page_id,
+                title: title.clone(),
+                old_rev_id: old_id,
+                new_rev_id: None,
+                timestamp: self.revisions[&old_id].timestamp,
+            });
+        }
+
+        let timestamp = self.tick();
+        let rev_id = self.next_id();
+        let (old_rev_id, old_len) = old.map_or((0, 0), |(id, len, _)| (id, len));
+        let new_len = text.len();
+        self.revisions.insert(
+            rev_id,
+            Revision {
+                id: rev_id,
+                parent_id: old_rev_id,
+                page_id,
+                timestamp,
+                user: user.to_owned(),
+                comment: comment.to_owned(),
+                minor,
+                text,
+            },
+        );
+        self.pages
+            .entry(title.clone())
+            .or_insert_with(|| Page {
+                id: page_id,
+                title: title.clone(),
+                revisions: Vec::new(),
+            })
+            .revisions
+            .push(rev_id);
+
+        let rc = RecentChange {
+            id: self.next_id(),
+            ty: if old.is_some() { "edit" } else { "new" },
+            title: title.clone(),
+            page_id,
+            rev_id,
+            old_rev_id,
+            user: user.to_owned(),
+            user_id: self.user_id(user),
+            old_len,
+            new_len,
+            timestamp,
+            comment: comment.to_owned(),
+            minor,
+            bot,
+        };
+        self.recent_changes.push(rc);
+
+        Ok(EditOutcome {
+            page_id,
+            title: title.clone(),
+            old_rev_id,
+            new_rev_id: Some(rev_id),
+            timestamp,
+        })
+    }
+
+    pub fn log_abuse(
+        &mut self,
+        filter_id: u64,
+        user: &str,
+        title: Title,
+        action: &str,
+        result: &str,
+    ) -> u64 {
+        let timestamp = self.tick();
+        let id = self.next_id();
+        self.abuse_log.push(AbuseLogEntry {
+            id,
+            filter_id,
+            user: user.to_owned(),
+            title,
+            action: action.to_owned(),
+            result: result.to_owned(),
+            timestamp,
+            rev_id: None,
+        });
+        id
+    }
+
+    /// The pages in a category, sorted by title.
+    pub fn category_members(&self, category: &Title) -> Vec<&Page> {
+        self.pages
+            .values()
+            .filter(|p| {
+                links(&self.latest(p).text)
+                    .iter()
+                    .any(|l| l.category && l.title == *category)
+            })
+            .collect()
+    }
+}
+
+/// What an edit is based on, to detect conflicts.
+#[derive(Clone, Copy, Debug)]
+pub enum Base {
+    RevId(u64),
+    Timestamp(DateTime<Utc>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Create {
+    Allow,
+    Only,
+    Never,
+}
+
+/// A `[[link]]` in wikitext.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Link {
+    pub title: Title,
+    /// `[[Category:...]]`, which puts the page in the category instead of linking to it.
+    pub category: bool,
+}
+
+/// The internal links of some wikitext, in order of appearance and without duplicates. Templates
+/// are not expanded.
+pub fn links(text: &str) -> Vec<Link> {
+    let mut out: Vec<Link> = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else { break };
+        let inner = &rest[..end];
+        rest = &rest[end + 2..];
+        let target = inner.split('|').next().unwrap_or_default();
+        let target = target.split('#').next().unwrap_or_default();
+        let colon = target.trim_start().starts_with(':');
+        let Some(title) = Title::parse(target) else {
+            continue;
+        };
+        if title.ns == NS_FILE && !colon {
+            continue;
+        }
+        let link = Link {
+            category: title.ns == NS_CATEGORY && !colon,
+            title,
+        };
+        if !out.contains(&link) {
+            out.push(link);
+        }
+    }
+    out
+}
@@ -0,0 +1,656 @@
+use std::sync::Arc;
+
+use fakewiki::FakeWiki;
+use futures_util::{StreamExt, TryStreamExt};
+use serde_json::Value;
+use wiki::api::error::ErrorCode;
+use wiki::api::{QueryResponse, RequestBuilderExt};
+use wiki::farm::WikiFarm;
+use wiki::req::abuse_log::{AbuseLogProp, ListAbuseLog};
+use wiki::req::block::{Block, Expiry};
+use wiki::req::category_members::{
+    CategoryMembersProp, CategoryMembersResponse, CategoryMembersType, ListCategoryMembers,
+};
+use wiki::req::parse::{Parse, ParseProp};
+use wiki::req::rc::{ListRc, RcProp, RcType};
+use wiki::req::{Action, Limit, NewText, PageSpec, Query, QueryList};
+use wiki::res::EditOutcome;
+use wiki::shutoff::ShutoffPage;
+use wiki::types::Namespace;
+use wiki::{AuthorizedAccess, BotPassword, ClientBuilder};
+
+/// Log in as `Example bot`, with `customize` setting further options on the builder.
+async fn bot(
+    fake: &FakeWiki,
+    server: &fakewiki::Server,
+    customize: impl FnOnce(ClientBuilder<AuthorizedAccess>) -> ClientBuilder<AuthorizedAccess>,
+) -> wiki::Bot {
+    fake.add_bot("Example bot", "hunter2");
+    let builder =
+        ClientBuilder::new(&server.api_url()).password(BotPassword::new("Example bot", "hunter2"));
+    customize(builder).build().await.unwrap()
+}
+
+fn block(user: &str) -> Action {
+    Action::Block(Block {
+        user: user.to_owned(),
+        expiry: Expiry::Never,
+        reason: None,
+        anononly: false,
+        nocreate: false,
+        autoblock: false,
+        noemail: false,
+        hidename: false,
+        allowusertalk: false,
+        reblock: false,
+        watchuser: false,
+        watchlistexpiry: None,
+        tags: None,
+        partial: false,
+        pagerestrictions: None,
+        namespace_restrictions: None,
+    })
+}
+
+#[tokio::test]
+async fn login_and_edit() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+    assert_eq!(bot.username(), Some("Example bot"));
+
+    bot.build_edit("Sandbox")
+        .text("Hello")
+        .summary("test")
+        .bot()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(fake.text("Sandbox").as_deref(), Some("Hello"));
+    assert_eq!(bot.fetch_content("Sandbox").await.unwrap(), "Hello");
+    assert_eq!(fake.history("Sandbox")[0].user, "Example bot");
+
+    let e = ClientBuilder::new(&server.api_url())
+        .password(BotPassword::new("Example bot", "wrong"))
+        .build()
+        .await
+        .unwrap_err();
+    assert!(matches!(e, wiki::Error::Login(_)));
+}
+
+#[tokio::test]
+async fn csrf_token_is_cached() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+
+    bot.build_edit("Sandbox").text("one").send().await.unwrap();
+    let fetched = fake.token_requests();
+    bot.build_edit("Sandbox").text("two").send().await.unwrap();
+    assert_eq!(fake.token_requests(), fetched);
+    assert_eq!(fake.text("Sandbox").as_deref(), Some("two"));
+}
+
+#[tokio::test]
+async fn bad_token_is_refetched_once() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+    bot.build_edit("Sandbox").text("one").send().await.unwrap();
+
+    let fetched = fake.token_requests();
+    fake.reject_tokens(1);
+    bot.build_edit("Sandbox").text("two").send().await.unwrap();
+    assert_eq!(fake.token_requests(), fetched + 1);
+    assert_eq!(fake.history("Sandbox").len(), 2);
+
+    // the new token is cached
+    bot.build_edit("Sandbox")
+        .text("three")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(fake.token_requests(), fetched + 1);
+}
+
+#[tokio::test]
+async fn bad_token_twice() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+    bot.build_edit("Sandbox").text("one").send().await.unwrap();
+
+    let fetched = fake.token_requests();
+    // a third attempt would succeed
+    fake.reject_tokens(2);
+    let e = bot
+        .build_edit("Sandbox")
+        .text("two")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(e.is_code(&ErrorCode::BadToken));
+    assert_eq!(fake.token_requests(), fetched + 1);
+    assert_eq!(fake.text("Sandbox").as_deref(), Some("one"));
+}
+
+#[tokio::test]
+async fn edit_conflict() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+    let base = fake.edit("Sandbox", "one");
+    fake.edit_as("Sandbox", "two", "Someone else", "");
+
+    let e = bot
+        .build_edit("Sandbox")
+        .text("three")
+        .baserevid(base)
+        .send()
+        .await
+        .unwrap_err();
+    assert!(e.is_code(&ErrorCode::EditConflict));
+    assert_eq!(fake.text("Sandbox").as_deref(), Some("two"));
+}
+
+#[tokio::test]
+async fn category_members_continue() {
+    let fake = FakeWiki::new();
+    for title in ["A", "B", "C", "Category:D"] {
+        fake.edit(title, "[[Category:Things]]");
+    }
+    fake.edit("E", "[[:Category:Things]]");
+    let server = fake.serve().await.unwrap();
+    let client = ClientBuilder::new(&server.api_url()).build().unwrap();
+
+    let q = Query {
+        list: Some(
+            QueryList::CategoryMembers(ListCategoryMembers {
+                spec: PageSpec::Title("Category:Things".into()),
+                limit: Limit::Value(1),
+                ty: CategoryMembersType::PAGE | CategoryMembersType::SUBCAT,
+                prop: CategoryMembersProp::TITLE | CategoryMembersProp::TYPE,
+                namespace: None,
+            })
+            .into(),
+        ),
+        ..Default::default()
+    };
+    let responses: Vec<Value> = client.query_all(q).try_collect().await.unwrap();
+    let titles: Vec<_> = responses
+        .iter()
+        .map(|v| v["query"]["categorymembers"][0]["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, ["A", "B", "C", "Category:D"]);
+}
+
+#[tokio::test]
+async fn abuse_log_and_parse() {
+    let fake = FakeWiki::new();
+    fake.log_abuse(1, "Vandal", "Sandbox", "edit");
+    fake.log_abuse(2, "Vandal", "Sandbox", "edit");
+    fake.log_abuse(1, "Vandal", "Other", "edit");
+    let rev = fake.edit(
+        "Sandbox",
+        "See [[Other]], [[missing|this]] and [[File:X.png]].",
+    );
+    fake.edit("Other", "");
+    let server = fake.serve().await.unwrap();
+    let client = ClientBuilder::new(&server.api_url()).build().unwrap();
+
+    let q = Query {
+        list: Some(
+            QueryList::AbuseLog(ListAbuseLog {
+                logid: None,
+                start: None,
+                end: None,
+                filter: Some(vec!["1".into()]),
+                limit: Limit::Value(1),
+                prop: AbuseLogProp::IDS | AbuseLogProp::TITLE,
+            })
+            .into(),
+        ),
+        ..Default::default()
+    };
+    let responses: Vec<Value> = client.query_all(q).try_collect().await.unwrap();
+    let titles: Vec<_> = responses
+        .iter()
+        .map(|v| v["query"]["abuselog"][0]["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, ["Other", "Sandbox"]);
+
+    let v: Value = client
+        .get(Action::Parse(Parse {
+            oldid: Some(rev),
+            prop: ParseProp::LINKS,
+            ..Default::default()
+        }))
+        .send_parse()
+        .await
+        .unwrap();
+    assert_eq!(
+        v["parse"]["links"],
+        serde_json::json!([
+            { "ns": 0, "title": "Other", "exists": true },
+            { "ns": 0, "title": "Missing", "exists": false },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn recent_changes_continue() {
+    let fake = FakeWiki::new();
+    fake.edit("A", "one");
+    fake.edit("B", "one");
+    fake.edit("A", "two");
+    let server = fake.serve().await.unwrap();
+    let client = ClientBuilder::new(&server.api_url()).build().unwrap();
+
+    let q = Query {
+        list: Some(
+            QueryList::RecentChanges(ListRc {
+                start: None,
+                end: None,
+                limit: Limit::Value(2),
+                prop: RcProp::TITLE | RcProp::IDS,
+                ty: RcType::EDIT | RcType::NEW,
+                namespace: None,
+            })
+            .into(),
+        ),
+        ..Default::default()
+    };
+    let responses: Vec<Value> = client.query_all(q).try_collect().await.unwrap();
+    let changes: Vec<_> = responses
+        .iter()
+        .flat_map(|v| v["query"]["recentchanges"].as_array().unwrap())
+        .map(|rc| (rc["type"].as_str().unwrap(), rc["title"].as_str().unwrap()))
+        .collect();
+    assert_eq!(changes, [("edit", "A"), ("new", "B"), ("new", "A")]);
+}
+
+#[tokio::test]
+async fn namespace_filter() {
+    let fake = FakeWiki::new();
+    fake.edit("A", "[[Category:Things]]");
+    fake.edit("Talk:A", "[[Category:Things]]");
+    fake.edit("Template:A", "[[Category:Things]]");
+    let server = fake.serve().await.unwrap();
+    let client = ClientBuilder::new(&server.api_url()).build().unwrap();
+
+    let q = Query {
+        list: Some(
+            QueryList::CategoryMembers(ListCategoryMembers {
+                spec: PageSpec::Title("Category:Things".into()),
+                limit: Limit::Max,
+                ty: CategoryMembersType::PAGE,
+                prop: CategoryMembersProp::TITLE,
+                namespace: Some(vec![Namespace::MAIN, Namespace::TEMPLATE]),
+            })
+            .into(),
+        ),
+        ..Default::default()
+    };
+    let res: QueryResponse<CategoryMembersResponse> =
+        client.get(Action::Query(q)).send_parse().await.unwrap();
+    let members: Vec<_> = res
+        .query
+        .categorymembers
+        .iter()
+        .map(|m| (m.ns.unwrap(), m.title.as_deref().unwrap()))
+        .collect();
+    assert_eq!(
+        members,
+        [(Namespace::MAIN, "A"), (Namespace::TEMPLATE, "Template:A")]
+    );
+}
+
+#[tokio::test]
+async fn site_info() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let client = ClientBuilder::new(&server.api_url()).build().unwrap();
+
+    let info = client.site_info().await.unwrap();
+    assert_eq!(info.namespace_by_name("WP").unwrap().id, Namespace::PROJECT);
+    assert_eq!(
+        info.page_url("Main Page").unwrap().as_str(),
+        format!("http://{}/wiki/Main_Page", server.addr())
+    );
+    // cached
+    drop(server);
+    assert!(Arc::ptr_eq(&info, &client.site_info().await.unwrap()));
+}
+
+#[tokio::test]
+async fn farm() {
+    let (en, commons, meta) = (FakeWiki::new(), FakeWiki::new(), FakeWiki::new());
+    en.edit("Main Page", "English");
+    commons.edit("Main Page", "Commons");
+    let en_server = en.serve().await.unwrap();
+    let commons_server = commons.serve().await.unwrap();
+    let meta_server = meta.serve().await.unwrap();
+    meta.add_to_farm("enwiki", &format!("http://{}", en_server.addr()));
+    meta.add_to_farm("commonswiki", &format!("http://{}", commons_server.addr()));
+
+    let client = ClientBuilder::new(&meta_server.api_url()).build().unwrap();
+    let farm = WikiFarm::load(&client).await.unwrap();
+    assert_eq!(farm.site("enwiki").unwrap().lang.as_deref(), Some("en"));
+    assert_eq!(
+        farm.api_url("commonswiki").unwrap(),
+        commons_server.api_url()
+    );
+    assert_eq!(farm.dbname(&en_server.api_url()), Some("enwiki"));
+
+    let mut texts: Vec<_> = farm
+        .fan_out(
+            ["enwiki", "commonswiki", "nowiki"],
+            2,
+            |client| async move { client.fetch_content("Main Page").await },
+        )
+        .map(|(dbname, res)| (dbname, res.ok()))
+        .collect()
+        .await;
+    texts.sort();
+    assert_eq!(
+        texts,
+        [
+            ("commonswiki".to_owned(), Some("Commons".to_owned())),
+            ("enwiki".to_owned(), Some("English".to_owned())),
+            ("nowiki".to_owned(), None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn edit_fetched_page() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+
+    let page = bot.fetch_page("Sandbox").await.unwrap();
+    assert!(!page.exists());
+    let res = bot.build_edit_from(&page).text("one").send().await.unwrap();
+    assert_eq!(res.outcome(), EditOutcome::Created);
+    assert_eq!(res.old_rev_id, Some(0));
+
+    let page = bot.fetch_page("Sandbox").await.unwrap();
+    assert_eq!(page.content.as_deref(), Some("one"));
+    assert_eq!(page.rev_id, res.new_rev_id);
+    let res = bot.build_edit_from(&page).text("one").send().await.unwrap();
+    assert_eq!(res.outcome(), EditOutcome::NoChange);
+
+    fake.edit_as("Sandbox", "two", "Someone else", "");
+    let e = bot
+        .build_edit_from(&page)
+        .text("three")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(e.is_code(&ErrorCode::EditConflict));
+
+    let page = bot.fetch_page("Sandbox").await.unwrap();
+    let res = bot
+        .build_edit_from(&page)
+        .text("three")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.outcome(), EditOutcome::Saved);
+    assert_eq!(fake.text("Sandbox").as_deref(), Some("three"));
+}
+
+#[tokio::test]
+async fn edit_page_retries_conflicts() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+    fake.edit("Sandbox", "one");
+
+    let mut seen = Vec::new();
+    let res = bot
+        .edit_page("Sandbox", |text| {
+            seen.push(text.to_owned());
+            if seen.len() == 1 {
+                fake.edit_as("Sandbox", "two", "Someone else", "");
+            }
+            Some(NewText::new(format!("{text}!")).summary("exclaim"))
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(seen, ["one", "two"]);
+    assert_eq!(res.outcome(), EditOutcome::Saved);
+    assert_eq!(fake.text("Sandbox").as_deref(), Some("two!"));
+    assert_eq!(fake.history("Sandbox").last().unwrap().comment, "exclaim");
+
+    let skipped = bot.edit_page("Sandbox", |_| None::<String>).await.unwrap();
+    assert!(skipped.is_none());
+
+    let e = bot
+        .edit_page_retrying("Sandbox", 0, |text| {
+            fake.edit_as("Sandbox", &format!("{text}?"), "Someone else", "");
+            Some("three")
+        })
+        .await
+        .unwrap_err();
+    assert!(e.is_code(&ErrorCode::EditConflict));
+}
+
+#[tokio::test]
+async fn bot_exclusion() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+    fake.edit("User talk:Alice", "{{bots|deny=Example bot}}");
+    fake.edit("User talk:Bob", "{{bots|optout=afd}}");
+
+    let e = bot
+        .build_edit("User talk:Alice")
+        .appendtext("Hi")
+        .check_exclusion()
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(e, wiki::Error::BotExcluded(_)));
+    assert_eq!(fake.history("User talk:Alice").len(), 1);
+
+    let e = bot
+        .build_edit("User talk:Bob")
+        .appendtext("Your article was nominated for deletion")
+        .message_type("afd")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(e, wiki::Error::BotExcluded(_)));
+
+    bot.build_edit("User talk:Bob")
+        .appendtext("Hi")
+        .check_exclusion()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(fake.history("User talk:Bob").len(), 2);
+}
+
+#[tokio::test]
+async fn edit_page_bot_exclusion() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+    fake.edit("User talk:Alice", "{{nobots}}");
+
+    let e = bot
+        .edit_page("User talk:Alice", |text| {
+            Some(NewText::new(format!("{text}\nHi")).check_exclusion())
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(e, wiki::Error::BotExcluded(_)));
+    assert_eq!(fake.history("User talk:Alice").len(), 1);
+}
+
+#[tokio::test]
+async fn shutoff_page() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    fake.edit("User:Example bot/Run", "true");
+    let bot = bot(&fake, &server, |b| {
+        b.shutoff(ShutoffPage::new("User:Example bot/Run", "true"))
+    })
+    .await;
+
+    bot.build_edit("Sandbox").text("one").send().await.unwrap();
+    fake.edit_as("User:Example bot/Run", "false", "Admin", "stop");
+    let e = bot
+        .build_edit("Sandbox")
+        .text("two")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(matches!(e, wiki::Error::ShutOff(_)));
+    assert_eq!(fake.text("Sandbox").as_deref(), Some("one"));
+    let e = bot
+        .post(block("Vandal"))
+        .send_and_report_err()
+        .await
+        .unwrap_err();
+    assert!(matches!(e, wiki::Error::ShutOff(_)));
+
+    // stays off even if the page is changed back
+    fake.edit_as("User:Example bot/Run", "true", "Admin", "");
+    assert!(matches!(
+        bot.check_shutoff().await,
+        Err(wiki::Error::ShutOff(_))
+    ));
+}
+
+#[tokio::test]
+async fn dry_run() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    fake.edit("Sandbox", "one\ntwo\n");
+    let bot = bot(&fake, &server, |b| b.dry_run()).await;
+
+    let res = bot
+        .build_edit("Sandbox")
+        .text("one\nthree\n")
+        .summary("test")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.outcome(), EditOutcome::Saved);
+    let res = bot.build_edit("New").text("new").send().await.unwrap();
+    assert_eq!(res.outcome(), EditOutcome::Created);
+    assert_eq!(fake.text("Sandbox").as_deref(), Some("one\ntwo\n"));
+    assert_eq!(fake.text("New"), None);
+
+    let journal = bot.journal();
+    assert_eq!(journal.len(), 2);
+    assert_eq!(journal[0].action, "edit");
+    assert!(journal[0]
+        .params
+        .contains(&("summary".to_owned(), "test".to_owned())));
+    assert_eq!(
+        journal[0].diff.as_deref(),
+        Some("--- Sandbox\n+++ Sandbox\n@@ -1,2 +1,2 @@\n one\n-two\n+three\n")
+    );
+}
+
+#[tokio::test]
+async fn dry_run_block() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b.dry_run()).await;
+
+    // the fake wiki does not know action=block, so this only succeeds if nothing is sent
+    let res = bot
+        .post(block("Vandal"))
+        .send_and_report_err()
+        .await
+        .unwrap();
+    assert_eq!(res["block"]["result"], "Success");
+
+    let journal = bot.journal();
+    assert_eq!(journal.len(), 1);
+    assert_eq!(journal[0].action, "block");
+    assert!(journal[0]
+        .params
+        .contains(&("user".to_owned(), "Vandal".to_owned())));
+}
+
+#[tokio::test]
+async fn dry_run_refused_edits() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    fake.edit("Sandbox", "one");
+    let bot = bot(&fake, &server, |b| b.dry_run()).await;
+
+    let e = bot
+        .build_edit("Sandbox")
+        .text("two")
+        .createonly()
+        .send()
+        .await
+        .unwrap_err();
+    assert!(e.is_code(&ErrorCode::ArticleExists));
+
+    let e = bot
+        .build_edit("Missing")
+        .text("two")
+        .nocreate()
+        .send()
+        .await
+        .unwrap_err();
+    assert!(e.is_code(&ErrorCode::MissingTitle));
+
+    let fetched = bot.fetch_page("Sandbox").await.unwrap();
+    fake.edit_as("Sandbox", "three", "Someone else", "");
+    let e = bot
+        .build_edit_from(&fetched)
+        .text("four")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(e.is_code(&ErrorCode::EditConflict));
+    assert!(bot.journal().is_empty());
+}
+
+#[tokio::test]
+async fn cookie_file_of_another_user() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    fake.add_user("Other", "hunter3");
+    let path = std::env::temp_dir().join(format!("fakewiki-cookies-{}.json", std::process::id()));
+    let other = ClientBuilder::new(&server.api_url())
+        .password(BotPassword::new("Other", "hunter3"))
+        .cookie_file(&path)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(other.username(), Some("Other"));
+
+    let bot = bot(&fake, &server, |b| b.cookie_file(&path)).await;
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(bot.username(), Some("Example bot"));
+    bot.build_edit("Sandbox").text("one").send().await.unwrap();
+    assert_eq!(fake.history("Sandbox")[0].user, "Example bot");
+}
+
+#[tokio::test]
+async fn expired_session() {
+    let fake = FakeWiki::new();
+    let server = fake.serve().await.unwrap();
+    let bot = bot(&fake, &server, |b| b).await;
+    fake.expire_sessions();
+
+    let e = bot
+        .build_edit("Sandbox")
+        .text("Hello")
+        .send()
+        .await
+        .unwrap_err();
+    assert!(e.is_assert_failed());
+    assert!(e.is_code(&ErrorCode::AssertUserFailed));
+    assert_eq!(fake.text("Sandbox"), None);
+}
@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use std::fs::File;
 
 use chrono::{DateTime, Duration, Utc};
@@ -138,7 +137,7 @@ pub fn search_back_to(
 
 pub fn de_regex<'de, D: Deserializer<'de>>(x: D) -> Result<Regex, D::Error> {
     let s = String::deserialize(x)?;
-    Regex::new(&s).map_err(|e| D::Error::custom(e))
+    Regex::new(&s).map_err(D::Error::custom)
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -255,7 +254,8 @@ async fn parse_filters(bot: &Bot, cfg: Vec<FilterDetails>) -> color_eyre::Result
 
 pub async fn catch_up() -> color_eyre::Result<JsonOutput> {
     let bot = ClientBuilder::enwiki()
-        .oauth(include_str!("../../bot_oauth.txt.secret"))
+        .credentials_from_env()?
+        .maxlag(5)
         .build()
         .await?;
 
@@ -363,18 +363,8 @@ pub async fn catch_up() -> color_eyre::Result<JsonOutput> {
     Ok(json)
 }
 
-pub struct CaseReport {
-    pub regex: String,
-    pub last_hit: u64,
-    pub last_hit_date: String,
-}
-
-pub struct Analyzed {
-    pub filters: HashMap<u32, HashMap<String, CaseReport>>,
-}
-
 pub async fn main() -> color_eyre::Result<()> {
-    let json = catch_up().await?;
+    catch_up().await?;
 
     Ok(())
 }
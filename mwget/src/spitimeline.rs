@@ -8,7 +8,7 @@ use wiki::api::QueryResponse;
 use wiki::req::contribs::{ListUserContribs, Selector, UserContribsProp};
 use wiki::req::events::{ListLogEvents, LogEventsProp};
 use wiki::req::{Limit, Query, QueryList};
-use wiki::Site;
+use wiki::farm::WikiFarm;
 
 #[derive(Serialize, Deserialize)]
 pub struct Event {
@@ -57,26 +57,27 @@ pub struct LogEvents {
     pub logevents: Vec<LogEvent>,
 }
 
+/// The wikis to search, by database name, with the label used for them in the output.
 pub const SITES: &[(&str, &str)] = &[
-    ("arwiki", "ar.wikipedia.org"),
-    ("commons", "commons.wikimedia.org"),
-    ("dewiki", "de.wikipedia.org"),
-    ("enwiki", "en.wikipedia.org"),
-    ("fawiki", "fa.wikipedia.org"),
-    ("frwiki", "fr.wikipedia.org"),
-    ("idwiki", "id.wikipedia.org"),
-    ("jawiki", "ja.wikipedia.org"),
-    ("kowiki", "ko.wikipedia.org"),
-    ("mswiki", "ms.wikipedia.org"),
-    ("nlwiki", "nl.wikipedia.org"),
-    ("ruwiki", "ru.wikipedia.org"),
-    ("svwiki", "sv.wikipedia.org"),
-    ("tawiki", "ta.wikipedia.org"),
-    ("thwiki", "th.wikipedia.org"),
-    ("ukwiki", "uk.wikipedia.org"),
-    ("viwiki", "vi.wikipedia.org"),
-    ("wd", "www.wikidata.org"),
-    ("zhwiki", "zh.wikipedia.org"),
+    ("arwiki", "arwiki"),
+    ("commonswiki", "commons"),
+    ("dewiki", "dewiki"),
+    ("enwiki", "enwiki"),
+    ("fawiki", "fawiki"),
+    ("frwiki", "frwiki"),
+    ("idwiki", "idwiki"),
+    ("jawiki", "jawiki"),
+    ("kowiki", "kowiki"),
+    ("mswiki", "mswiki"),
+    ("nlwiki", "nlwiki"),
+    ("ruwiki", "ruwiki"),
+    ("svwiki", "svwiki"),
+    ("tawiki", "tawiki"),
+    ("thwiki", "thwiki"),
+    ("ukwiki", "ukwiki"),
+    ("viwiki", "viwiki"),
+    ("wikidatawiki", "wd"),
+    ("zhwiki", "zhwiki"),
 ];
 
 pub const IPS: &[&str] = &[
@@ -128,9 +129,10 @@ pub fn sort() -> crate::Result<()> {
 pub async fn main() -> crate::Result<()> {
     let mut events = vec![];
 
-    for (name, url) in SITES {
-        let api_url = format!("https://{url}/w/api.php");
-        let site = Site::new(&api_url)?;
+    let farm = WikiFarm::wikimedia().await?;
+    for (dbname, name) in SITES {
+        let site = farm.client(dbname)?;
+        let url = &farm.site(dbname).expect("listed in the site matrix").url;
 
         let q = Query {
             list: Some(
@@ -150,6 +152,7 @@ pub async fn main() -> crate::Result<()> {
                         | UserContribsProp::TITLE
                         | UserContribsProp::FLAGS,
                     limit: Limit::Max,
+                    namespace: None,
                 })
                 .into(),
             ),
@@ -174,7 +177,7 @@ pub async fn main() -> crate::Result<()> {
                             ),
                             comment: contrib.comment,
                             link: format!(
-                                "https://{url}/w/index.php?diff=prev&oldid={}&diffmode=source",
+                                "{url}/w/index.php?diff=prev&oldid={}&diffmode=source",
                                 contrib.revid
                             ),
                         })
@@ -223,7 +226,7 @@ pub async fn main() -> crate::Result<()> {
                                 description: format!("type: {type_}, action: {action}"),
                                 comment,
                                 link: format!(
-                                    "https://{url}/w/index.php?title=Special:Log&logid={logid}"
+                                    "{url}/w/index.php?title=Special:Log&logid={logid}"
                                 ),
                             })
                         }
@@ -11,13 +11,14 @@ use wiki::req::category_members::{
     ListCategoryMembers,
 };
 use wiki::req::parse::{Parse as RParse, ParseProp};
-use wiki::req::{Action, EditBuilder, Limit, PageSpec, Query, QueryList};
+use wiki::req::{Action, Limit, PageSpec, Query, QueryList};
+use wiki::types::Namespace;
 use wiki::{Bot, ClientBuilder};
 
 #[derive(Deserialize, Debug)]
 pub struct Link {
     pub exists: bool,
-    pub ns: i64,
+    pub ns: Namespace,
     pub title: String,
 }
 
@@ -47,7 +48,7 @@ async fn handle(
     for member in res.query.categorymembers {
         match member {
             CategoryMember {
-                ns: Some(0),
+                ns: Some(Namespace::MAIN),
                 title: Some(title),
                 ty: Some(ty),
                 ..
@@ -69,6 +70,7 @@ async fn handle(
                                     | CategoryMembersProp::TYPE
                                     | CategoryMembersProp::TITLE,
                                 limit: Limit::Max,
+                                namespace: None,
                             })
                             .into(),
                         ),
@@ -91,7 +93,7 @@ async fn main() -> wiki::Result<()> {
         .init();
     let stream = wiki::events::ReqwestSseStream::recent_changes().await?;
     let bot = ClientBuilder::enwiki()
-        .oauth(include_str!("../../bot_oauth.txt.secret"))
+        .credentials_from_env()?
         .build()
         .await?;
     let botr = &bot;
@@ -106,6 +108,7 @@ async fn main() -> wiki::Result<()> {
                         | CategoryMembersProp::TYPE
                         | CategoryMembersProp::TITLE,
                     limit: Limit::Max,
+                    namespace: None,
                 })
                 .into(),
             ),
@@ -125,7 +128,7 @@ async fn main() -> wiki::Result<()> {
                             new: Some(new),
                         }),
                     wiki: Some(wiki),
-                    namespace: Some(0),
+                    namespace: Some(Namespace::MAIN),
                     ..
                 } if wiki == "enwiki" => {
                     let res: Response = botr
@@ -145,19 +148,16 @@ async fn main() -> wiki::Result<()> {
                         .parse
                         .links
                         .into_iter()
-                        .filter(|l| l.ns == 0)
+                        .filter(|l| l.ns == Namespace::MAIN)
                         .map(|l| l.title)
                         .collect();
                     for Link { title, ns, .. } in res2.parse.links {
-                        if prev_links.contains(&title) || ns != 0 || !bad_pages.contains(&title) {
+                        if prev_links.contains(&title) || ns != Namespace::MAIN || !bad_pages.contains(&title) {
                             continue;
                         }
-                        let token = botr.get_csrf_token().await?;
-                        let edit = EditBuilder::new()
-                            .title("title")
+                        botr.build_edit("title")
                             .summary("Loggin action")
-                            .appendtext(format!("*https://en.wikipedia.org/w/index.php?oldid={old}&diff={new}&diffmode=source\n")).token(token.token).bot().build();
-                        botr.post(Action::Edit(edit)).send_and_report_err().await?;
+                            .appendtext(format!("*https://en.wikipedia.org/w/index.php?oldid={old}&diff={new}&diffmode=source\n")).bot().send().await?;
                     }
                 }
                 _ => {}
@@ -1,22 +1,34 @@
 use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 
-use futures_util::TryStreamExt;
+use futures_util::{TryFutureExt, TryStreamExt};
+use reqwest::header::RETRY_AFTER;
 use reqwest::Url;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde_json::Value;
-use tracing::debug;
+use tracing::{debug, warn};
 
+use self::error::ApiErrors;
+use self::warning::ApiWarning;
+use crate::credentials::redact_url;
 use crate::generators::GenGen;
+use crate::oauth::OAuth2Session;
 use crate::req::{self, Main, PageSpec, TokenType};
 use crate::res::PageResponse;
+use crate::retry::{self, is_transient_status};
 use crate::sealed::Access;
+use crate::transport::{Body, HttpRequest, Transport};
+use crate::types::{MwTimestamp, Namespace};
 use crate::url::WriteUrlParams;
 #[cfg(target_arch = "wasm32")]
 use crate::url::{TriStr, UrlParamWriter};
-use crate::Result;
+use crate::{Client, Result};
+
+pub mod error;
+pub mod warning;
 
 #[macro_export]
 macro_rules! build_response_type {
@@ -64,7 +76,7 @@ build_response_type! {
 
 #[derive(Deserialize, PartialEq, Eq, Debug)]
 pub struct BasicSearchResult {
-    pub ns: u16,
+    pub ns: Namespace,
     pub title: String,
     #[serde(rename = "pageid")]
     pub page_id: usize,
@@ -74,7 +86,7 @@ pub struct BasicSearchResult {
 pub struct RecentChangesResult {
     #[serde(rename = "type")]
     pub type_: String,
-    pub ns: Option<u16>,
+    pub ns: Option<Namespace>,
     pub title: Option<String>,
     pub pageid: Option<usize>,
     pub revid: Option<usize>,
@@ -105,9 +117,9 @@ pub struct Slot {
 #[derive(Deserialize, Debug)]
 pub struct Revision<S> {
     #[serde(rename = "revid")]
-    pub rev_id: u32,
+    pub rev_id: u64,
     #[serde(rename = "parentid")]
-    pub parent_id: u32,
+    pub parent_id: u64,
     pub slots: S,
 }
 
@@ -119,6 +131,38 @@ pub struct MaybeContinue<T> {
     pub inner: T,
 }
 
+/// A response body together with the warnings the API sent along with it.
+#[derive(Debug)]
+pub struct Response<T> {
+    pub warnings: Vec<ApiWarning>,
+    pub meta: ResponseMeta,
+    pub body: T,
+}
+
+/// The fields added to a response by the [`GlobalParams`](req::GlobalParams) of the request.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ResponseMeta {
+    pub curtimestamp: Option<MwTimestamp>,
+    pub servedby: Option<String>,
+    pub requestid: Option<String>,
+}
+
+impl ResponseMeta {
+    /// Take the fields out of a response.
+    pub fn from_response(v: &mut Value) -> Result<Self> {
+        let Some(obj) = v.as_object_mut() else {
+            return Ok(Self::default());
+        };
+        let mut meta = serde_json::Map::new();
+        for key in ["curtimestamp", "servedby", "requestid"] {
+            if let Some(v) = obj.remove(key) {
+                meta.insert(key.to_owned(), v);
+            }
+        }
+        Ok(serde_json::from_value(Value::Object(meta))?)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Q2<A, B> {
     #[serde(flatten)]
@@ -129,29 +173,46 @@ pub struct Q2<A, B> {
 
 macro_rules! token {
     ($Name:ident = $field:literal = [$($t:expr),+$(,)?] + $token:ident) => {
-        #[derive(Deserialize, Debug)]
+        #[derive(Deserialize, Debug, Clone)]
         pub struct $Name {
             #[serde(rename = $field)]
             pub $token: String,
         }
         impl Token for $Name {
             fn types() -> TokenType { $($t)|* }
+            fn from_value($token: String) -> Self { Self { $token } }
+            fn value(&self) -> &str { &self.$token }
         }
     };
 }
 
-token!(LoginToken = "logintoken" = [TokenType::LOGIN] + token);
+token!(CreateAccountToken = "createaccounttoken" = [TokenType::CREATE_ACCOUNT] + token);
 token!(CsrfToken = "csrftoken" = [TokenType::CSRF] + token);
+token!(
+    DeleteGlobalAccountToken =
+        "deleteglobalaccounttoken" = [TokenType::DELETE_GLOBAL_ACCOUNT] + token
+);
+token!(LoginToken = "logintoken" = [TokenType::LOGIN] + token);
+token!(PatrolToken = "patroltoken" = [TokenType::PATROL] + token);
+token!(RollbackToken = "rollbacktoken" = [TokenType::ROLLBACK] + token);
+token!(
+    SetGlobalAccountStatusToken =
+        "setglobalaccountstatustoken" = [TokenType::SET_GLOBAL_ACCOUNT_STATUS] + token
+);
+token!(UserRightsToken = "userrightstoken" = [TokenType::USER_RIGHTS] + token);
+token!(WatchToken = "watchtoken" = [TokenType::WATCH] + token);
 
 pub trait Token: DeserializeOwned {
     fn types() -> TokenType;
+    fn from_value(token: String) -> Self;
+    fn value(&self) -> &str;
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Page<S> {
     #[serde(rename = "pageid")]
     pub page_id: u32,
-    pub ns: u8,
+    pub ns: Namespace,
     pub title: String,
     pub revisions: Vec<Revision<S>>,
 }
@@ -200,7 +261,7 @@ pub fn mkurl(mut url: Url, m: Main) -> Url {
         q.add(TriStr::Static("origin"), TriStr::Static("*"));
     }
     url.set_query(Some(&q.0));
-    debug!(%url, "GET");
+    debug!(url = %redact_url(&url), "GET");
     url
 }
 
@@ -220,17 +281,23 @@ pub fn mkurl_with_ext(
         q.add(TriStr::Static("origin"), TriStr::Static("*"));
     }
     url.set_query(Some(&q.0));
-    debug!(%url, "GET");
+    debug!(url = %redact_url(&url), "GET");
     Ok(url)
 }
 
 mod sealed {
     pub trait Sealed {}
-    impl Sealed for reqwest::RequestBuilder {}
+    impl<A: crate::sealed::Access> Sealed for super::ApiRequest<A> {}
 }
 
 pub trait RequestBuilderExt: Sized + sealed::Sealed {
-    fn send_and_report_err(self) -> BoxFuture<crate::Result<Value>>;
+    /// Send the request, returning the response body along with the warnings the API reported.
+    ///
+    /// Warnings are also logged through `tracing`.
+    fn send_and_report(self) -> BoxFuture<crate::Result<Response<Value>>>;
+    fn send_and_report_err(self) -> BoxFuture<crate::Result<Value>> {
+        Box::pin(self.send_and_report().map_ok(|r| r.body))
+    }
     fn send_parse<D: DeserializeOwned>(self) -> BoxFuture<crate::Result<D>>
     where
         Self: Send + Sync + 'static,
@@ -240,6 +307,24 @@ pub trait RequestBuilderExt: Sized + sealed::Sealed {
             Ok(serde_json::from_value(v)?)
         })
     }
+    /// Like [`send_parse`](Self::send_parse), but keeps the warnings.
+    fn send_parse_with_warnings<D: DeserializeOwned>(self) -> BoxFuture<crate::Result<Response<D>>>
+    where
+        Self: Send + Sync + 'static,
+    {
+        Box::pin(async move {
+            let Response {
+                warnings,
+                meta,
+                body,
+            } = self.send_and_report().await?;
+            Ok(Response {
+                warnings,
+                meta,
+                body: serde_json::from_value(body)?,
+            })
+        })
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -248,15 +333,202 @@ pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send + Sync>>;
 #[cfg(target_arch = "wasm32")]
 pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
 
-impl RequestBuilderExt for reqwest::RequestBuilder {
-    fn send_and_report_err(self) -> BoxFuture<crate::Result<Value>> {
-        Box::pin(async {
-            let r = self.send().await?;
-            let mut v = r.json::<Value>().await?;
-            if let Some(v) = v.get_mut("error") {
-                Err(crate::Error::MediaWiki(v.take()))
-            } else {
-                Ok(v)
+/// Send the request once. Also returns the delay the server asked for in `Retry-After`.
+pub(crate) async fn execute(
+    transport: &dyn Transport,
+    req: HttpRequest,
+) -> (Result<Response<Value>>, Option<Duration>) {
+    let r = match transport.send(req).await {
+        Ok(r) => r,
+        Err(e) => return (Err(e), None),
+    };
+    let retry_after = r
+        .headers
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+        .map(Duration::from_secs);
+    let res = (|| {
+        if is_transient_status(r.status) {
+            return Err(crate::Error::HttpStatus(r.status));
+        }
+        let mut v: Value = serde_json::from_slice(&r.body)?;
+        let warnings = ApiWarning::from_response(&mut v);
+        warnings.iter().for_each(ApiWarning::log);
+        let meta = ResponseMeta::from_response(&mut v)?;
+        if let Some(e) = ApiErrors::from_response(&mut v) {
+            Err(crate::Error::MediaWiki(e))
+        } else {
+            Ok(Response {
+                warnings,
+                meta,
+                body: v,
+            })
+        }
+    })();
+    (res, retry_after)
+}
+
+/// How the parameters of an [`ApiRequest`] are sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestMethod {
+    /// In the query string of a GET request, or as a POST body like [`Post`](Self::Post) if the
+    /// URL would be longer than allowed by
+    /// [`ClientBuilder::max_url_length`](crate::ClientBuilder::max_url_length).
+    Get,
+    /// As an `application/x-www-form-urlencoded` POST body.
+    Post,
+    /// As a `multipart/form-data` POST body.
+    Multipart,
+}
+
+/// A request to the API that has not been sent yet, created by [`Client::get`] and
+/// [`Client::post`].
+///
+/// Sending it applies the client's [`RetryPolicy`](crate::retry::RetryPolicy).
+pub struct ApiRequest<A: Access> {
+    site: Client<A>,
+    method: RequestMethod,
+    main: Main,
+    ext: Option<Value>,
+}
+
+impl<A: Access> ApiRequest<A> {
+    pub fn new(site: Client<A>, method: RequestMethod, mut main: Main) -> Self {
+        if main.maxlag.is_none() {
+            main.maxlag = site.retry.maxlag;
+        }
+        if main.assert.is_none() {
+            main.assert = site.assert;
+        }
+        if main.assertuser.is_none() && site.assert.is_some() {
+            main.assertuser.clone_from(&site.user);
+        }
+        main.global = main.global.or(&site.global);
+        Self {
+            site,
+            method,
+            main,
+            ext: None,
+        }
+    }
+
+    /// Send extra parameters along with the request, such as `continue` values from a previous
+    /// response.
+    pub fn ext(mut self, ext: Value) -> Self {
+        self.ext = Some(ext);
+        self
+    }
+
+    /// Send the parameters as a `multipart/form-data` body.
+    pub fn multipart(mut self) -> Self {
+        self.method = RequestMethod::Multipart;
+        self
+    }
+
+    pub fn main(&self) -> &Main {
+        &self.main
+    }
+
+    pub fn method(&self) -> RequestMethod {
+        self.method
+    }
+
+    fn build(&self) -> Result<HttpRequest> {
+        let url = self.site.url.clone();
+        let req = match self.method {
+            RequestMethod::Get => {
+                let get = match &self.ext {
+                    Some(ext) => mkurl_with_ext(url.clone(), self.main.clone(), ext.clone())?,
+                    None => mkurl(url.clone(), self.main.clone()),
+                };
+                if get.as_str().len() <= self.site.max_url_len {
+                    HttpRequest::get(get)
+                } else {
+                    debug!(
+                        len = get.as_str().len(),
+                        "URL too long, sending as POST instead"
+                    );
+                    self.form(url)?
+                }
+            }
+            RequestMethod::Post => self.form(url)?,
+            RequestMethod::Multipart => {
+                let mut fields = Vec::new();
+                if let Err(e) = self.main.ser(&mut fields) {
+                    match e {}
+                }
+                if let Some(Value::Object(ext)) = &self.ext {
+                    for (k, v) in ext {
+                        let v = match v {
+                            Value::String(s) => s.clone(),
+                            v => v.to_string(),
+                        };
+                        fields.push((k.clone(), v));
+                    }
+                }
+                debug!(%url, "POST");
+                HttpRequest::post(url, Body::Multipart(fields))
+            }
+        };
+        self.site.authorize(req)
+    }
+
+    /// A POST request with the parameters as an `application/x-www-form-urlencoded` body.
+    fn form(&self, url: Url) -> Result<HttpRequest> {
+        let mut q = crate::url::Simple::default();
+        if let Err(e) = self.main.ser(&mut q) {
+            match e {}
+        }
+        if let Some(ext) = &self.ext {
+            q.add_serde(ext)?;
+        }
+        debug!(%url, "POST");
+        Ok(HttpRequest::post(url, Body::Form(q.0)))
+    }
+}
+
+impl<A: Access> RequestBuilderExt for ApiRequest<A> {
+    fn send_and_report(self) -> BoxFuture<crate::Result<Response<Value>>> {
+        Box::pin(async move {
+            if self.main.action.is_write() {
+                self.site.check_shutoff().await?;
+                if let Some(journal) = &self.site.journal {
+                    let body = journal.record(&self.site, &self.main).await?;
+                    return Ok(Response {
+                        warnings: Vec::new(),
+                        meta: ResponseMeta::default(),
+                        body,
+                    });
+                }
+            }
+            let mut attempt = 0;
+            let mut refreshed = false;
+            loop {
+                let req = self.build()?;
+                let sent_with = OAuth2Session::sent_with(&req);
+                let (res, retry_after) = execute(self.site.transport(), req).await;
+                let err = match res {
+                    Ok(res) => return Ok(res),
+                    Err(e) => e.classify_assert(),
+                };
+                if !refreshed && self.site.refresh_oauth2(&err, sent_with.as_deref()).await? {
+                    refreshed = true;
+                    continue;
+                }
+                let delay = if self.main.action.is_write() {
+                    self.site.retry.write_delay(attempt, &err, retry_after)
+                } else {
+                    self.site.retry.delay(attempt, &err, retry_after)
+                };
+                let Some(delay) = delay else {
+                    return Err(err);
+                };
+                warn!(%err, attempt, "request failed, retrying in {delay:?}");
+                if !retry::sleep(delay).await {
+                    return Err(err);
+                }
+                attempt += 1;
             }
         })
     }
@@ -264,12 +536,10 @@ impl RequestBuilderExt for reqwest::RequestBuilder {
 
 impl<A: Access> crate::Client<A> {
     pub async fn get_tokens<T: Token>(&self) -> Result<T> {
-        let res = self
-            .client
-            .get(mkurl(self.url.clone(), Main::tokens(T::types())))
-            .send()
+        let tokens: QueryResponse<Tokens<T>> = self
+            .request(RequestMethod::Get, Main::tokens(T::types()))
+            .send_parse()
             .await?;
-        let tokens: QueryResponse<Tokens<T>> = res.json().await?;
         Ok(tokens.query.tokens)
     }
 }
@@ -277,8 +547,8 @@ impl<A: Access> crate::Client<A> {
 pub type QueryAllGenerator<A> = GenGen<
     A,
     Main,
-    fn(&Url, &reqwest::Client, &Main) -> Main,
-    fn(&Url, &reqwest::Client, &Main, Value) -> Result<Vec<Value>>,
+    fn(&Client<A>, &Main) -> Main,
+    fn(&Client<A>, &Main, Value) -> Result<Vec<Value>>,
     Value,
     Value,
 >;
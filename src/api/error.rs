@@ -0,0 +1,236 @@
+//! Errors reported by the API in the `error` or `errors` fields of a response.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+macro_rules! error_codes {
+    ($($(#[$meta:meta])* $Variant:ident = $code:literal,)*) => {
+        /// A machine-readable error code returned by the API.
+        ///
+        /// Codes that bots commonly need to react to have their own variant, everything else is
+        /// kept as [`ErrorCode::Other`].
+        #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum ErrorCode {
+            $($(#[$meta])* $Variant,)*
+            Other(String),
+        }
+
+        impl ErrorCode {
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$Variant => $code,)*
+                    Self::Other(code) => code,
+                }
+            }
+        }
+
+        impl From<String> for ErrorCode {
+            fn from(code: String) -> Self {
+                match &*code {
+                    $($code => Self::$Variant,)*
+                    _ => Self::Other(code),
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    /// The CSRF (or other) token supplied was invalid, usually because the session expired.
+    BadToken = "badtoken",
+    EditConflict = "editconflict",
+    /// Replication lag exceeded the `maxlag` parameter sent with the request.
+    MaxLag = "maxlag",
+    RateLimited = "ratelimited",
+    Blocked = "blocked",
+    AutoBlocked = "autoblocked",
+    ProtectedPage = "protectedpage",
+    CascadeProtected = "cascadeprotected",
+    MissingTitle = "missingtitle",
+    NoSuchPageId = "nosuchpageid",
+    NoSuchRevId = "nosuchrevid",
+    ArticleExists = "articleexists",
+    PageDeleted = "pagedeleted",
+    InvalidTitle = "invalidtitle",
+    PermissionDenied = "permissiondenied",
+    ReadOnly = "readonly",
+    NotLoggedIn = "notloggedin",
+    MustBePosted = "mustbeposted",
+    SpamBlacklist = "spamblacklist",
+    ContentTooBig = "contenttoobig",
+    BadValue = "badvalue",
+    MissingParam = "missingparam",
+    AbuseFilterWarning = "abusefilter-warning",
+    AbuseFilterDisallowed = "abusefilter-disallowed",
+    /// The request was not made as the kind of user required by `assert`.
+    AssertAnonFailed = "assertanonfailed",
+    AssertUserFailed = "assertuserfailed",
+    AssertBotFailed = "assertbotfailed",
+    /// The request was not made as the user named by `assertuser`.
+    AssertNamedUserFailed = "assertnameduserfailed",
+    /// The OAuth credentials were rejected, e.g. because an OAuth 2 access token expired.
+    InvalidAuthorization = "mwoauth-invalid-authorization",
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl From<&str> for ErrorCode {
+    fn from(code: &str) -> Self {
+        code.to_owned().into()
+    }
+}
+
+/// A single error returned by the API.
+#[derive(Clone, Debug)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    /// The human-readable message. This is `info` in the default error format, and `text` or
+    /// `html` when `errorformat` is set.
+    pub info: String,
+    /// Link to the API help page.
+    pub docref: Option<String>,
+    /// Path of the module that reported this error. Only sent when `errorformat` is set.
+    pub module: Option<String>,
+    /// Module-specific data, such as `lag` for `maxlag` or `blockinfo` for `blocked`.
+    pub data: Option<Value>,
+}
+
+impl ApiError {
+    /// Parse the legacy `{"error": {"code": .., "info": ..}}` format.
+    fn from_bc(mut obj: Map<String, Value>) -> Self {
+        let code = take_string(&mut obj, "code").unwrap_or_default().into();
+        let info = take_string(&mut obj, "info").unwrap_or_default();
+        let docref = take_string(&mut obj, "docref");
+        // the help text for the module, added by formatversion=1.
+        obj.remove("*");
+        Self {
+            code,
+            info,
+            docref,
+            module: None,
+            data: (!obj.is_empty()).then_some(Value::Object(obj)),
+        }
+    }
+
+    /// Parse an element of `errors` as produced by `errorformat=plaintext|wikitext|html|raw|none`.
+    fn from_errorformat(mut obj: Map<String, Value>, docref: Option<String>) -> Self {
+        let code = take_string(&mut obj, "code").unwrap_or_default().into();
+        let info = ["text", "html", "*", "key"]
+            .into_iter()
+            .find_map(|k| take_string(&mut obj, k))
+            .unwrap_or_default();
+        Self {
+            code,
+            info,
+            docref,
+            module: take_string(&mut obj, "module"),
+            data: obj.remove("data"),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.info.is_empty() {
+            write!(f, "{}", self.code)
+        } else {
+            write!(f, "{}: {}", self.code, self.info)
+        }
+    }
+}
+
+/// All errors contained in an API response. There is always at least one.
+///
+/// The default error format only ever returns one error, but `errorformat` makes the API
+/// report every error encountered while handling the request.
+#[derive(Clone, Debug)]
+pub struct ApiErrors {
+    errors: Vec<ApiError>,
+}
+
+impl ApiErrors {
+    /// Extract the errors out of a response, if there are any.
+    pub fn from_response(v: &mut Value) -> Option<Self> {
+        let obj = v.as_object_mut()?;
+        if let Some(Value::Object(e)) = obj.remove("error") {
+            return Some(Self {
+                errors: vec![ApiError::from_bc(e)],
+            });
+        }
+        let Some(Value::Array(errors)) = obj.remove("errors") else {
+            return None;
+        };
+        let docref = take_string(obj, "docref");
+        let errors: Vec<_> = errors
+            .into_iter()
+            .filter_map(|e| match e {
+                Value::Object(e) => Some(ApiError::from_errorformat(e, docref.clone())),
+                _ => None,
+            })
+            .collect();
+        (!errors.is_empty()).then_some(Self { errors })
+    }
+
+    /// The first error reported.
+    pub fn first(&self) -> &ApiError {
+        &self.errors[0]
+    }
+
+    /// The code of the first error reported.
+    pub fn code(&self) -> &ErrorCode {
+        &self.first().code
+    }
+
+    /// Whether any of the errors has the specified code.
+    pub fn has_code(&self, code: &ErrorCode) -> bool {
+        self.errors.iter().any(|e| e.code == *code)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ApiError> {
+        self.errors.iter()
+    }
+
+    pub fn into_vec(self) -> Vec<ApiError> {
+        self.errors
+    }
+}
+
+impl From<ApiError> for ApiErrors {
+    fn from(e: ApiError) -> Self {
+        Self { errors: vec![e] }
+    }
+}
+
+impl<'a> IntoIterator for &'a ApiErrors {
+    type Item = &'a ApiError;
+    type IntoIter = std::slice::Iter<'a, ApiError>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Display for ApiErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for e in &self.errors {
+            if !first {
+                f.write_str("; ")?;
+            }
+            first = false;
+            e.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn take_string(obj: &mut Map<String, Value>, key: &str) -> Option<String> {
+    match obj.remove(key)? {
+        Value::String(s) => Some(s),
+        _ => None,
+    }
+}
@@ -0,0 +1,87 @@
+//! Warnings reported by the API in the `warnings` field of a response.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tracing::warn;
+
+/// A single warning returned by the API, such as an unrecognized parameter or a truncated result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiWarning {
+    /// Path of the module that reported this warning, e.g. `main` or `query+revisions`.
+    pub module: String,
+    /// The warning code. Only sent when `errorformat` is set.
+    pub code: Option<String>,
+    pub text: String,
+    /// Extra data for the warning. Only sent when `errorformat` is set.
+    pub data: Option<Value>,
+}
+
+impl ApiWarning {
+    /// Extract the warnings out of a response, removing them from the value.
+    pub fn from_response(v: &mut Value) -> Vec<Self> {
+        match v.as_object_mut().and_then(|o| o.remove("warnings")) {
+            // the legacy format: `{"module": {"warnings": "text\ntext"}}`
+            Some(Value::Object(modules)) => modules
+                .into_iter()
+                .flat_map(|(module, v)| {
+                    let text = match v {
+                        Value::Object(mut o) => o
+                            .remove("warnings")
+                            .or_else(|| o.remove("*"))
+                            .and_then(|v| v.as_str().map(ToOwned::to_owned)),
+                        _ => None,
+                    };
+                    let text = text.unwrap_or_default();
+                    text.lines()
+                        .map(|text| Self {
+                            module: module.clone(),
+                            code: None,
+                            text: text.to_owned(),
+                            data: None,
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+            // `errorformat` is set: `[{"code": .., "text": .., "module": ..}]`
+            Some(Value::Array(warnings)) => warnings
+                .into_iter()
+                .filter_map(|w| match w {
+                    Value::Object(o) => Some(Self::from_errorformat(o)),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    fn from_errorformat(mut obj: Map<String, Value>) -> Self {
+        let mut take = |k: &str| match obj.remove(k) {
+            Some(Value::String(s)) => Some(s),
+            _ => None,
+        };
+        let code = take("code");
+        let module = take("module").unwrap_or_default();
+        let text = ["text", "html", "*", "key"]
+            .into_iter()
+            .find_map(&mut take)
+            .unwrap_or_default();
+        Self {
+            module,
+            code,
+            text,
+            data: obj.remove("data"),
+        }
+    }
+
+    /// Emit this warning as a `tracing` event.
+    pub fn log(&self) {
+        warn!(module = %self.module, code = self.code.as_deref(), "API warning: {}", self.text);
+    }
+}
+
+impl fmt::Display for ApiWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.module, self.text)
+    }
+}
@@ -6,6 +6,7 @@ use std::num::{NonZeroU16, NonZeroU32, NonZeroU64, NonZeroUsize};
 use std::ops::Deref;
 
 use crate::req::PageSpec;
+use crate::types::Namespace;
 use crate::url::{BufferedName, TriStr, UrlParamWriter, WriteUrlValue};
 
 impl Deref for TriStr<'_> {
@@ -41,7 +42,7 @@ impl From<String> for TriStr<'_> {
     }
 }
 
-impl<'w, T: UrlParamWriter> UrlParamWriter for &'w mut T {
+impl<T: UrlParamWriter> UrlParamWriter for &mut T {
     type E = T::E;
     fn add(&mut self, name: TriStr<'_>, value: TriStr<'_>) -> Result<(), Self::E> {
         (*self).add(name, value)
@@ -70,6 +71,7 @@ display_impls! {
     NonZeroU32,
     NonZeroU64,
     NonZeroUsize,
+    Namespace,
 }
 
 impl From<&'_ str> for PageSpec {
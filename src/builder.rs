@@ -1,32 +1,165 @@
 use std::borrow::Cow;
 use std::marker::PhantomData;
 use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 
 use http_types::Url;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
-use serde_json::Value;
+#[cfg(target_arch = "wasm32")]
+use reqwest::header::HeaderMap;
+use reqwest::header::HeaderValue;
+use serde_json::json;
 use tracing::{debug, info};
 
-use crate::api::{LoginToken, QueryResponse, RequestBuilderExt, UserInfo, UserInfoInner};
-use crate::req::{self, Login, Main};
+use crate::api::{
+    LoginToken, QueryResponse, RequestBuilderExt, RequestMethod, UserInfo, UserInfoInner,
+};
+use crate::credentials::{Credentials, Secret};
+use crate::login::{
+    ClientLoginInfo, ClientLoginResponse, ClientLoginStatus, LoginResponse, OathCallback,
+};
+use crate::oauth::{OAuth1Credentials, OAuth2Credentials, OAuth2Session};
+use crate::req::{self, Assert, ClientLogin, GlobalParams, Login, Main};
+use crate::retry::RetryPolicy;
 use crate::sealed::Access;
-use crate::{AnonymousAccess, AuthorizedAccess, BotPassword, Client, Result, UA};
+use crate::shutoff::{Shutoff, ShutoffPage};
+use crate::transport::{ReqwestTransport, SessionTransport, Transport};
+use crate::{
+    cookies, AnonymousAccess, AuthorizedAccess, BotPassword, Client, Error, Result,
+    DEFAULT_MAX_URL_LEN, UA,
+};
 
 pub struct ClientBuilder<A: Access> {
+    settings: Settings,
+    auth: Auth,
+    /// A misuse of the builder, reported by `build`.
+    error: Option<&'static str>,
+    _ph: PhantomData<A>,
+}
+
+/// Everything but the credentials, kept as is when the builder gets them.
+struct Settings {
     url: String,
     client: reqwest::ClientBuilder,
+    transport: Option<Arc<dyn Transport>>,
     user_agent: Option<Cow<'static, str>>,
-    oauth: Option<String>,
-    password: Option<BotPassword>,
-    _ph: PhantomData<A>,
+    cookie_file: Option<PathBuf>,
+    assert: Option<Assert>,
+    retry: RetryPolicy,
+    max_url_len: usize,
+    global: GlobalParams,
+    shutoff: Option<ShutoffPage>,
+    dry_run: bool,
+}
+
+/// How the client logs in.
+enum Auth {
+    Anonymous,
+    Credentials(Credentials),
+    ClientLogin(ClientLoginCredentials),
+}
+
+struct ClientLoginCredentials {
+    username: String,
+    password: Secret,
+    /// Two-factor codes, if the account needs them.
+    oath: Option<OathCallback>,
 }
 
 impl<A: Access> ClientBuilder<A> {
     pub fn user_agent(mut self, ua: impl Into<Cow<'static, str>>) -> Self {
-        self.user_agent = Some(ua.into());
+        self.settings.user_agent = Some(ua.into());
+        self
+    }
+
+    /// Set how failed requests are retried.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.settings.retry = retry;
+        self
+    }
+
+    /// Send requests with this transport instead of [`ReqwestTransport`].
+    ///
+    /// The user agent and cookie jar still apply: requests get the `User-Agent` and `Cookie`
+    /// headers unless they already have them, and `Set-Cookie` headers of responses are stored.
+    pub fn transport(mut self, transport: impl Transport) -> Self {
+        self.settings.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Restore the session cookies from this file if it exists, and save them there after
+    /// logging in. When logging in with a password, the login is skipped if the restored
+    /// session is still valid.
+    pub fn cookie_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.settings.cookie_file = Some(path.into());
         self
     }
+
+    /// Send `maxlag` with every request, and wait and retry when the wiki reports that
+    /// replication lag exceeds it. Wikimedia recommends `5` for bots.
+    pub fn maxlag(mut self, maxlag: u32) -> Self {
+        self.settings.retry.maxlag = Some(maxlag);
+        self
+    }
+
+    /// Send read requests as POST instead of GET when their URL would be longer than this, as
+    /// servers refuse overly long URLs. Defaults to 8000 bytes.
+    pub fn max_url_length(mut self, len: usize) -> Self {
+        self.settings.max_url_len = len;
+        self
+    }
+
+    /// Send these parameters with every request, unless the request sets them itself.
+    pub fn global_params(mut self, params: GlobalParams) -> Self {
+        self.settings.global = params;
+        self
+    }
+
+    /// Get messages in this language, such as `en` for a bot that parses error messages on a
+    /// wiki in another language.
+    pub fn uselang(mut self, lang: impl Into<String>) -> Self {
+        self.settings.global.uselang = Some(lang.into());
+        self
+    }
+
+    /// Convert content to this language variant, such as `zh-hans` on Chinese Wikipedia.
+    pub fn variant(mut self, variant: impl Into<String>) -> Self {
+        self.settings.global.variant = Some(variant.into());
+        self
+    }
+}
+
+impl Settings {
+    /// The transport set with [`ClientBuilder::transport`], or reqwest, configured with the user
+    /// agent and cookie jar.
+    fn make_transport(&mut self, cookies: &cookies::Jar) -> Result<Arc<dyn Transport>> {
+        let ua = self.user_agent.as_deref().unwrap_or(UA);
+        if let Some(inner) = self.transport.take() {
+            return Ok(Arc::new(SessionTransport {
+                inner,
+                user_agent: HeaderValue::from_str(ua)?,
+                cookies: cookies.clone(),
+            }));
+        }
+        let mut client = std::mem::take(&mut self.client);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            client = client.cookie_provider(cookies.clone()).user_agent(ua);
+        }
+
+        // TODO add cookie store support once lands in reqwest
+        // https://github.com/seanmonstar/reqwest/pull/1449
+        #[cfg(target_arch = "wasm32")]
+        {
+            let mut headers = HeaderMap::new();
+            headers.insert("Api-User-Agent", HeaderValue::from_str(ua)?);
+            client = client.default_headers(headers);
+        }
+
+        Ok(Arc::new(ReqwestTransport::new(client.build()?)))
+    }
 }
 
 impl ClientBuilder<AnonymousAccess> {
@@ -35,11 +168,21 @@ impl ClientBuilder<AnonymousAccess> {
         let client = reqwest::Client::builder();
 
         Self {
-            client,
-            url: api_url.to_owned(),
-            user_agent: None,
-            oauth: None,
-            password: None,
+            settings: Settings {
+                client,
+                transport: None,
+                url: api_url.to_owned(),
+                user_agent: None,
+                cookie_file: None,
+                assert: Some(Assert::User),
+                retry: RetryPolicy::default(),
+                max_url_len: DEFAULT_MAX_URL_LEN,
+                global: GlobalParams::default(),
+                shutoff: None,
+                dry_run: false,
+            },
+            auth: Auth::Anonymous,
+            error: None,
             _ph: PhantomData,
         }
     }
@@ -57,143 +200,323 @@ impl ClientBuilder<AnonymousAccess> {
     }
 
     pub fn password(self, pass: BotPassword) -> ClientBuilder<AuthorizedAccess> {
-        ClientBuilder {
-            url: self.url,
-            client: self.client,
-            user_agent: self.user_agent,
-            oauth: None,
-            password: Some(pass),
-            _ph: PhantomData,
-        }
+        self.authorize(Auth::Credentials(Credentials::BotPassword(pass)))
+    }
+
+    /// Log in with the main account password through `action=clientlogin`.
+    ///
+    /// Use this for accounts that cannot use bot passwords. If the account has two-factor
+    /// authentication enabled, supply the codes with [`ClientBuilder::oath`].
+    pub fn client_login(
+        self,
+        username: impl Into<String>,
+        password: impl Into<Secret>,
+    ) -> ClientBuilder<AuthorizedAccess> {
+        self.authorize(Auth::ClientLogin(ClientLoginCredentials {
+            username: username.into(),
+            password: password.into(),
+            oath: None,
+        }))
     }
 
     /// to login via oauth, go to
     /// <https://meta.wikimedia.org/wiki/Special:OAuthConsumerRegistration/propose/oauth2>
     /// and create an owner-only application.
-    pub fn oauth(self, token: impl Into<String>) -> ClientBuilder<AuthorizedAccess> {
+    pub fn oauth(self, token: impl Into<Secret>) -> ClientBuilder<AuthorizedAccess> {
+        self.authorize(Auth::Credentials(Credentials::OAuth(token.into())))
+    }
+
+    /// Sign every request with the credentials of an OAuth 1.0a consumer.
+    pub fn oauth1(self, credentials: OAuth1Credentials) -> ClientBuilder<AuthorizedAccess> {
+        self.authorize(Auth::Credentials(Credentials::OAuth1(credentials)))
+    }
+
+    /// Use OAuth 2 access tokens obtained with a refresh token. The access token is refreshed
+    /// automatically when it expires; see [`Client::oauth2_refresh_token`] for saving the new
+    /// refresh token.
+    pub fn oauth2(self, credentials: OAuth2Credentials) -> ClientBuilder<AuthorizedAccess> {
+        self.authorize(Auth::Credentials(Credentials::OAuth2(credentials)))
+    }
+
+    /// Log in with any kind of credentials.
+    pub fn credentials(self, credentials: Credentials) -> ClientBuilder<AuthorizedAccess> {
+        self.authorize(Auth::Credentials(credentials))
+    }
+
+    fn authorize(self, auth: Auth) -> ClientBuilder<AuthorizedAccess> {
         ClientBuilder {
-            url: self.url,
-            client: self.client,
-            user_agent: self.user_agent,
-            oauth: Some(token.into()),
-            password: None,
+            settings: self.settings,
+            auth,
+            error: self.error,
             _ph: PhantomData,
         }
     }
 
-    /// build anonymous access
-    pub fn build(mut self) -> Result<Client<AnonymousAccess>> {
-        let url: Url = self.url.parse()?;
-        assert!(url.query().is_none());
-        let ua = self.user_agent.as_deref().unwrap_or(UA);
+    /// Log in with credentials from `WIKI_*` environment variables. See
+    /// [`Credentials::from_env`].
+    pub fn credentials_from_env(self) -> Result<ClientBuilder<AuthorizedAccess>> {
+        Ok(self.credentials(Credentials::from_env()?))
+    }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            self.client = self.client.cookie_store(true).user_agent(ua);
-        }
+    /// Log in with credentials from a file of `key = value` lines. See
+    /// [`Credentials::from_file`].
+    pub fn credentials_from_file(
+        self,
+        path: impl AsRef<Path>,
+    ) -> Result<ClientBuilder<AuthorizedAccess>> {
+        Ok(self.credentials(Credentials::from_file(path)?))
+    }
 
-        // TODO add cookie store support once lands in reqwest
-        // https://github.com/seanmonstar/reqwest/pull/1449
-        #[cfg(target_arch = "wasm32")]
-        {
-            let mut headers = HeaderMap::new();
-            headers.insert("Api-User-Agent", HeaderValue::from_str(ua)?);
-            self.client = self.client.default_headers(headers);
-        }
+    /// Log in with credentials from a directory with one file per key. See
+    /// [`Credentials::from_dir`].
+    pub fn credentials_from_dir(
+        self,
+        dir: impl AsRef<Path>,
+    ) -> Result<ClientBuilder<AuthorizedAccess>> {
+        Ok(self.credentials(Credentials::from_dir(dir)?))
+    }
+
+    /// build anonymous access
+    pub fn build(self) -> Result<Client<AnonymousAccess>> {
+        let mut settings = self.settings;
+        let url: Url = settings.url.parse()?;
+        assert!(url.query().is_none());
+        let cookies = cookies::load(settings.cookie_file.as_deref())?;
+        let transport = settings.make_transport(&cookies)?;
 
         Ok(Client {
-            client: self.client.build()?,
+            transport,
             url,
+            retry: settings.retry,
+            max_url_len: settings.max_url_len,
+            global: settings.global,
+            tokens: Default::default(),
+            site_info: Default::default(),
+            oauth1: None,
+            oauth2: None,
+            cookies,
+            assert: None,
+            user: None,
+            shutoff: None,
+            journal: None,
             acc: PhantomData,
         })
     }
 }
 
 impl ClientBuilder<AuthorizedAccess> {
-    /// build by logging in.
-    pub async fn build(mut self) -> Result<Client<AuthorizedAccess>> {
-        let url: Url = self.url.parse()?;
-        assert!(url.query().is_none());
-        let ua = self.user_agent.as_deref().unwrap_or(UA);
-
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            self.client = self.client.cookie_store(true).user_agent(ua);
+    /// Supply two-factor codes when `clientlogin` asks for them. Only applies to
+    /// [`ClientBuilder::client_login`]; building fails if another way of logging in is used.
+    pub fn oath(
+        mut self,
+        f: impl FnMut(&ClientLoginInfo) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        match &mut self.auth {
+            Auth::ClientLogin(cl) => cl.oath = Some(Box::new(f)),
+            _ => self.error = Some("two-factor codes can only be supplied for client_login"),
         }
+        self
+    }
 
-        let mut headers = HeaderMap::new();
+    /// Which kind of user every request has to be made as, once logged in. Defaults to
+    /// [`Assert::User`]; `None` disables the check.
+    ///
+    /// Requests also assert the name of the user, so that an expired session fails with
+    /// [`Error::AssertFailed`](crate::Error::AssertFailed) instead of editing logged out.
+    pub fn assert(mut self, assert: Option<Assert>) -> Self {
+        self.settings.assert = assert;
+        self
+    }
 
-        #[cfg(target_arch = "wasm32")]
-        {
-            headers.insert("Api-User-Agent", HeaderValue::from_str(ua)?);
-        }
+    /// Stop writing once the content of a page is no longer as expected, so that anyone can shut
+    /// the bot off by editing its run page. Writes fail with
+    /// [`Error::ShutOff`](crate::Error::ShutOff) from then on; see also
+    /// [`Client::check_shutoff`].
+    pub fn shutoff(mut self, page: ShutoffPage) -> Self {
+        self.settings.shutoff = Some(page);
+        self
+    }
 
-        if let Some(token) = self.oauth {
-            headers.insert(
-                AUTHORIZATION,
-                HeaderValue::from_str(&format!("Bearer {token}"))?,
-            );
-        }
+    /// Record writes, such as edits and blocks, in [`Client::journal`] instead of sending them.
+    /// They are answered with a made-up successful response, or for edits, with the error the
+    /// wiki would give.
+    pub fn dry_run(mut self) -> Self {
+        self.settings.dry_run = true;
+        self
+    }
 
-        self.client = self.client.default_headers(headers);
+    /// build by logging in.
+    pub async fn build(self) -> Result<Client<AuthorizedAccess>> {
+        if let Some(e) = self.error {
+            return Err(Error::CustomStatic(e));
+        }
+        let mut settings = self.settings;
+        let url: Url = settings.url.parse()?;
+        assert!(url.query().is_none());
+        let restored = settings.cookie_file.as_deref().is_some_and(Path::exists);
+        let cookies = cookies::load(settings.cookie_file.as_deref())?;
+        let transport = settings.make_transport(&cookies)?;
 
-        let site = Client {
-            client: self.client.build()?,
+        let (mut password, mut main_login, mut oauth1, mut oauth2) = (None, None, None, None);
+        match self.auth {
+            Auth::Anonymous => {}
+            Auth::Credentials(Credentials::BotPassword(pass)) => password = Some(pass),
+            Auth::Credentials(Credentials::OAuth(token)) => {
+                oauth2 = Some(OAuth2Session::from_access_token(token.expose().to_owned()))
+            }
+            Auth::Credentials(Credentials::OAuth1(creds)) => oauth1 = Some(creds),
+            Auth::Credentials(Credentials::OAuth2(creds)) => {
+                oauth2 = Some(OAuth2Session::new(creds, &url)?)
+            }
+            Auth::ClientLogin(cl) => main_login = Some(cl),
+        }
+        let mut site = Client {
+            transport,
             url,
+            retry: settings.retry,
+            max_url_len: settings.max_url_len,
+            global: settings.global,
+            tokens: Default::default(),
+            site_info: Default::default(),
+            oauth1: oauth1.map(Arc::new),
+            oauth2: oauth2.map(Arc::new),
+            cookies,
+            assert: None,
+            user: None,
+            shutoff: settings.shutoff.map(|page| Arc::new(Shutoff::new(page))),
+            journal: settings.dry_run.then(Default::default),
             acc: PhantomData,
         };
 
-        if let Some(pass) = self.password {
-            let LoginToken { token } = site.get_tokens::<LoginToken>().await?;
-            let req = site.client.post(site.url.clone());
-            let l = Main::login(Login {
-                name: pass.username,
-                password: pass.password,
-                token,
-            });
-            let form = l.build_form();
-            let v: Value = req.multipart(form).send_and_report_err().await?;
-            debug!("{v}");
-            if !v
-                .get("login")
-                .and_then(|v| v.get("result"))
-                .map_or(false, |v| v == "Success")
-            {
-                panic!("Vandalism detected. Your actions will be logged at [[WP:LTA/BotAbuser]]")
+        if let Some(oauth2) = &site.oauth2 {
+            oauth2.refresh(site.transport(), None).await?;
+        }
+
+        let needs_login = password.is_some() || main_login.is_some();
+        let mut user = None;
+        if restored && needs_login {
+            let info = userinfo(&site).await?;
+            let requested = match (&password, &main_login) {
+                (Some(pass), _) => pass.username.as_str(),
+                (None, Some(cl)) => cl.username.as_str(),
+                (None, None) => unreachable!(),
+            };
+            if is_ip(&info.name) {
+                debug!("restored session has expired, logging in again");
+            } else if !same_user(requested, &info.name) {
+                debug!(user = %info.name, "restored session is of another user, logging in again");
+                cookies::clear(&site.cookies);
+            } else {
+                user = Some(info);
+            }
+        }
+
+        if user.is_none() {
+            if let Some(pass) = password {
+                let LoginToken { token } = site.get_tokens::<LoginToken>().await?;
+                let l = Main::login(Login {
+                    name: pass.username,
+                    password: pass.password.expose().to_owned(),
+                    token,
+                });
+                let res: LoginResponse = site
+                    .request(RequestMethod::Multipart, l)
+                    .send_parse()
+                    .await?;
+                debug!("{res:?}");
+                res.login.into_result()?;
+            } else if let Some(cl) = main_login {
+                client_login(&site, cl).await?;
             }
         }
 
         // we have built the site, now we need to check that we are actually logged in.
-        let QueryResponse {
-            query:
-                UserInfo {
-                    userinfo:
-                        UserInfoInner {
-                            id,
-                            name,
-                            extra: (),
-                        },
-                },
-        } = site
-            .client
-            .execute(
-                site.get(req::Action::Query(req::Query {
-                    meta: Some(req::QueryMeta::UserInfo(req::MetaUserInfo { prop: None }).into()),
-                    ..Default::default()
-                }))
-                .build()?,
-            )
-            .await?
-            .json()
-            .await?;
+        let UserInfoInner { id, name, .. } = match user {
+            Some(user) => user,
+            None => userinfo(&site).await?,
+        };
 
         info!("Logged in as \"{name}\" (id {id})");
 
         // if we are an IP, then we are definitely not logged in.
-        if Ipv4Addr::from_str(&name).is_ok() || Ipv6Addr::from_str(&name).is_ok() {
+        if is_ip(&name) {
             return Err(crate::Error::Unauthorized);
         }
 
+        if let Some(path) = &settings.cookie_file {
+            site.save_cookies(path)?;
+        }
+
+        site.assert = settings.assert;
+        site.user = Some(name);
+
         Ok(site)
     }
 }
+
+async fn userinfo(site: &Client<AuthorizedAccess>) -> Result<UserInfoInner<()>> {
+    let res: QueryResponse<UserInfo<()>> = site
+        .get(req::Action::Query(req::Query {
+            meta: Some(req::QueryMeta::UserInfo(req::MetaUserInfo { prop: None }).into()),
+            ..Default::default()
+        }))
+        .send_parse()
+        .await?;
+    Ok(res.query.userinfo)
+}
+
+/// Whether `name`, as given to log in, is the user `actual` reported by the wiki. Bot passwords
+/// log in as `User@BotName`, and MediaWiki capitalizes the first letter and uses spaces.
+fn same_user(name: &str, actual: &str) -> bool {
+    let name = name.split_once('@').map_or(name, |(user, _)| user);
+    let name = name.replace('_', " ");
+    let mut chars = name.trim().chars();
+    let name: String = chars
+        .next()
+        .into_iter()
+        .flat_map(char::to_uppercase)
+        .chain(chars)
+        .collect();
+    name == actual
+}
+
+fn is_ip(name: &str) -> bool {
+    Ipv4Addr::from_str(name).is_ok() || Ipv6Addr::from_str(name).is_ok()
+}
+
+async fn client_login(
+    site: &Client<AuthorizedAccess>,
+    mut cl: ClientLoginCredentials,
+) -> Result<()> {
+    let LoginToken { token } = site.get_tokens::<LoginToken>().await?;
+    let mut req = ClientLogin {
+        returnurl: Some(site.url.to_string()),
+        cont: false,
+        token,
+    };
+    let mut fields = json!({ "username": cl.username, "password": cl.password.expose() });
+    loop {
+        let res: ClientLoginResponse = site
+            .request(
+                RequestMethod::Post,
+                Main::action(req::Action::ClientLogin(req.clone())),
+            )
+            .ext(fields)
+            .send_parse()
+            .await?;
+        let info = res.client_login;
+        debug!(status = ?info.status, message = info.message, "clientlogin");
+        match info.status {
+            ClientLoginStatus::Pass => return Ok(()),
+            ClientLoginStatus::Ui if info.wants_field("OATHToken") => {
+                let Some(code) = cl.oath.as_mut().and_then(|f| f(&info)) else {
+                    return Err(info.into_error().into());
+                };
+                fields = json!({ "OATHToken": code });
+                req.returnurl = None;
+                req.cont = true;
+            }
+            _ => return Err(info.into_error().into()),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Saving the session cookies to a file and restoring them, so that a process does not have to
+//! log in again every time it starts.
+
+use std::path::Path;
+
+use crate::Result;
+
+/// The cookie jar shared by a client and all of its clones.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) type Jar = std::sync::Arc<reqwest_cookie_store::CookieStoreMutex>;
+
+/// Cookies are managed by the browser.
+#[cfg(target_arch = "wasm32")]
+pub(crate) type Jar = ();
+
+/// Load the cookies saved at `path`, or start with an empty jar if there is no such file.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn load(path: Option<&Path>) -> Result<Jar> {
+    use std::fs::File;
+    use std::io::{BufReader, ErrorKind};
+
+    use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+
+    let store = match path.map(File::open) {
+        Some(Ok(f)) => {
+            cookie_store::serde::json::load_all(BufReader::new(f)).map_err(crate::Error::Cookies)?
+        }
+        Some(Err(e)) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+        _ => CookieStore::default(),
+    };
+    Ok(Jar::new(CookieStoreMutex::new(store)))
+}
+
+/// Save the cookies to `path`, readable only by the owner on Unix since they hold the session.
+///
+/// Session cookies are included: MediaWiki keeps the login in a cookie without an expiry date.
+/// Expired cookies are left out.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn save(jar: &Jar, path: &Path) -> Result<()> {
+    use std::convert::Infallible;
+    use std::fs::OpenOptions;
+
+    use reqwest_cookie_store::CookieStore;
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let f = options.open(path)?;
+    // the mode only applies to new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        f.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    let mut f = std::io::BufWriter::new(f);
+    let unexpired = {
+        let store = jar.lock().unwrap();
+        let cookies = store.iter_unexpired().cloned().map(Ok::<_, Infallible>);
+        match CookieStore::from_cookies(cookies, false) {
+            Ok(store) => store,
+            Err(e) => match e {},
+        }
+    };
+    cookie_store::serde::json::save_incl_expired_and_nonpersistent(&unexpired, &mut f)
+        .map_err(crate::Error::Cookies)
+}
+
+/// Forget every cookie, such as the session of another user.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn clear(jar: &Jar) {
+    jar.lock().unwrap().clear();
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn load(_: Option<&Path>) -> Result<Jar> {
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn save(_: &Jar, _: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn clear(_: &Jar) {}
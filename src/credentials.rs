@@ -0,0 +1,227 @@
+//! Loading credentials from outside the source code, and keeping them out of logs.
+//!
+//! [`Credentials`] are read from environment variables, a config file or a credentials directory,
+//! all using the same names:
+//!
+//! | Credentials | Keys |
+//! |---|---|
+//! | Owner-only OAuth 2 token | `oauth_token` |
+//! | OAuth 1.0a | `consumer_key`, `consumer_secret`, `access_token`, `access_secret` |
+//! | OAuth 2 with refresh | `client_id`, `client_secret`, `refresh_token` |
+//! | Bot password | `username`, `password` |
+//!
+//! ```no_run
+//! # async fn f() -> wiki::Result<()> {
+//! // WIKI_USERNAME=Example@bot WIKI_PASSWORD=... cargo run
+//! let bot = wiki::ClientBuilder::enwiki()
+//!     .credentials_from_env()?
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use reqwest::Url;
+
+use crate::oauth::{OAuth1Credentials, OAuth2Credentials};
+use crate::{BotPassword, Error, Result};
+
+/// The prefix of environment variables read by [`Credentials::from_env`].
+pub const ENV_PREFIX: &str = "WIKI_";
+
+/// A password, token or other secret. Its `Debug` output never contains the value.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// The value, to be sent to the wiki.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret([redacted])")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(s: String) -> Self {
+        Self(s)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(s: &str) -> Self {
+        Self(s.to_owned())
+    }
+}
+
+/// How to log in, as loaded by the functions of this type or the `credentials_from_*` methods
+/// of [`ClientBuilder`](crate::ClientBuilder).
+#[derive(Clone, Debug)]
+pub enum Credentials {
+    BotPassword(BotPassword),
+    /// The access token of an owner-only OAuth 2 consumer.
+    OAuth(Secret),
+    OAuth1(OAuth1Credentials),
+    OAuth2(OAuth2Credentials),
+}
+
+impl Credentials {
+    /// Read `WIKI_OAUTH_TOKEN`, `WIKI_USERNAME` and so on.
+    pub fn from_env() -> Result<Self> {
+        Self::from_env_prefixed(ENV_PREFIX)
+    }
+
+    /// Read environment variables with another prefix, to keep the credentials of several bots
+    /// apart.
+    pub fn from_env_prefixed(prefix: &str) -> Result<Self> {
+        Self::from_lookup("the environment", |key| {
+            std::env::var(format!("{prefix}{}", key.to_ascii_uppercase())).ok()
+        })
+    }
+
+    /// Read a file of `key = value` lines. Empty lines and lines starting with `#` are ignored.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let mut values = HashMap::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                Error::Credentials(format!(
+                    "line {} of {} is not `key = value`",
+                    n + 1,
+                    path.display()
+                ))
+            })?;
+            values.insert(key.trim().to_owned(), value.trim().to_owned());
+        }
+        Self::from_lookup(&path.display().to_string(), |key| values.get(key).cloned())
+    }
+
+    /// Read a directory with one file per key, such as the `$CREDENTIALS_DIRECTORY` of a systemd
+    /// service or mounted container secrets.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(Error::Credentials(format!(
+                "{} is not a directory",
+                dir.display()
+            )));
+        }
+        Self::from_lookup(&dir.display().to_string(), |key| {
+            let value = fs::read_to_string(dir.join(key)).ok()?;
+            Some(value.trim_end_matches(['\r', '\n']).to_owned())
+        })
+    }
+
+    fn from_lookup(source: &str, get: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let require = |key: &str| {
+            get(key).ok_or_else(|| Error::Credentials(format!("{key} is missing from {source}")))
+        };
+        if let Some(token) = get("oauth_token") {
+            Ok(Self::OAuth(token.into()))
+        } else if let Some(key) = get("consumer_key") {
+            Ok(Self::OAuth1(OAuth1Credentials::new(
+                key,
+                require("consumer_secret")?,
+                require("access_token")?,
+                require("access_secret")?,
+            )))
+        } else if let Some(id) = get("client_id") {
+            Ok(Self::OAuth2(OAuth2Credentials::new(
+                id,
+                require("client_secret")?,
+                require("refresh_token")?,
+            )))
+        } else if let Some(username) = get("username") {
+            Ok(Self::BotPassword(BotPassword::new(
+                username,
+                require("password")?,
+            )))
+        } else {
+            Err(Error::Credentials(format!("no credentials in {source}")))
+        }
+    }
+}
+
+/// Whether the value of a parameter is a token, password or other secret that must not be
+/// logged.
+pub fn is_secret_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with("token") || name.ends_with("password") || name.ends_with("secret")
+}
+
+const REDACTED: &str = "[redacted]";
+
+/// The URL with the values of secret parameters replaced, for logging.
+pub fn redact_url(url: &Url) -> Url {
+    if !url.query_pairs().any(|(k, _)| is_secret_param(&k)) {
+        return url.clone();
+    }
+    let mut redacted = url.clone();
+    redacted
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(url.query_pairs().map(|(k, v)| {
+            let v = if is_secret_param(&k) {
+                REDACTED.into()
+            } else {
+                v
+            };
+            (k, v)
+        }));
+    redacted
+}
+
+/// Form fields with the values of secret parameters replaced, for logging.
+pub(crate) fn redact_fields<'a>(
+    fields: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Vec<(&'a str, &'a str)> {
+    fields
+        .into_iter()
+        .map(|(k, v)| (k, if is_secret_param(k) { REDACTED } else { v }))
+        .collect()
+}
+
+/// Replace the values of secret keys anywhere in a JSON response, such as the tokens of
+/// `meta=tokens`. Returns whether anything was replaced.
+pub(crate) fn redact_json(value: &mut serde_json::Value) -> bool {
+    use serde_json::Value;
+
+    let mut redacted = false;
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                if is_secret_param(k) && !v.is_null() {
+                    *v = REDACTED.into();
+                    redacted = true;
+                } else {
+                    redacted |= redact_json(v);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for v in items {
+                redacted |= redact_json(v);
+            }
+        }
+        _ => {}
+    }
+    redacted
+}
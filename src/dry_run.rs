@@ -0,0 +1,181 @@
+//! Dry runs, which record the writes a bot would make instead of sending them.
+//!
+//! ```no_run
+//! # async fn f() -> wiki::Result<()> {
+//! let bot = wiki::ClientBuilder::enwiki()
+//!     .credentials_from_env()?
+//!     .dry_run()
+//!     .build()
+//!     .await?;
+//! bot.build_edit("Wikipedia:Sandbox").appendtext("Hello").send().await?;
+//! for entry in bot.journal() {
+//!     println!("{}", entry.diff.as_deref().unwrap_or_default());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+use similar::TextDiff;
+use tracing::info;
+
+use crate::api::error::{ApiError, ErrorCode};
+use crate::credentials::redact_fields;
+use crate::req::{Action, Edit, EditSection, Main};
+use crate::res::FetchedPage;
+use crate::types::MwTimestamp;
+use crate::url::WriteUrlParams;
+use crate::{sealed, Client, Error, Result};
+
+/// The token sent by users who are not logged in, used in place of a real token.
+pub(crate) const PLACEHOLDER_TOKEN: &str = "+\\";
+
+/// A write that was recorded instead of being sent.
+#[derive(Clone, Debug)]
+pub struct JournalEntry {
+    /// The `action` parameter, such as `edit` or `block`.
+    pub action: String,
+    /// The parameters of the request, with secrets redacted.
+    pub params: Vec<(String, String)>,
+    /// For edits, a unified diff of the current text of the page and the text that would have
+    /// been saved. `None` for section edits and undos, whose result depends on the server.
+    pub diff: Option<String>,
+}
+
+/// The writes recorded by a client in dry-run mode, shared by its clones.
+#[derive(Default)]
+pub(crate) struct Journal(Mutex<Vec<JournalEntry>>);
+
+impl Journal {
+    pub(crate) fn entries(&self) -> Vec<JournalEntry> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// Record a write instead of sending it and make up a successful response to it. Edits
+    /// that the wiki would refuse fail with the same error.
+    pub(crate) async fn record<A: sealed::Access>(
+        &self,
+        client: &Client<A>,
+        main: &Main,
+    ) -> Result<Value> {
+        let (diff, response) = match &main.action {
+            Action::Edit(edit) => simulate_edit(client, edit).await?,
+            _ => (None, None),
+        };
+
+        let mut fields = Vec::new();
+        if let Err(e) = main.ser(&mut fields) {
+            match e {}
+        }
+        let params: Vec<(String, String)> = redact_fields(fields.iter().map(|(k, v)| (&**k, &**v)))
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        let name = params
+            .iter()
+            .find(|(k, _)| k == "action")
+            .map(|(_, v)| v.clone())
+            .unwrap_or_default();
+        info!(
+            action = %name,
+            ?params,
+            diff = diff.as_deref().unwrap_or_default(),
+            "dry run, not sending"
+        );
+
+        let response = response.unwrap_or_else(|| json!({ &name: { "result": "Success" } }));
+        self.0.lock().unwrap().push(JournalEntry {
+            action: name,
+            params,
+            diff,
+        });
+        Ok(response)
+    }
+}
+
+/// The diff of an edit and the response the API would give to it.
+async fn simulate_edit<A: sealed::Access>(
+    client: &Client<A>,
+    edit: &Edit,
+) -> Result<(Option<String>, Option<Value>)> {
+    let page = client.fetch_page(edit.spec.clone()).await?;
+    if let Some((code, info)) = refusal(edit, &page) {
+        return Err(Error::MediaWiki(
+            ApiError {
+                code,
+                info: info.to_owned(),
+                docref: None,
+                module: None,
+                data: None,
+            }
+            .into(),
+        ));
+    }
+    let current = page.content.as_deref().unwrap_or_default();
+    let proposed = match (&edit.text, &edit.section) {
+        (Some(text), None) => Some(text.clone()),
+        (Some(text), Some(EditSection::New { title })) => {
+            let mut new = current.to_owned();
+            if !new.is_empty() {
+                new.push_str("\n\n");
+            }
+            new.push_str(&format!("== {title} ==\n\n{text}"));
+            Some(new)
+        }
+        (None, None) if edit.prependtext.is_some() || edit.appendtext.is_some() => Some(format!(
+            "{}{current}{}",
+            edit.prependtext.as_deref().unwrap_or_default(),
+            edit.appendtext.as_deref().unwrap_or_default()
+        )),
+        _ => None,
+    };
+    let diff = proposed.as_deref().map(|proposed| {
+        TextDiff::from_lines(current, proposed)
+            .unified_diff()
+            .header(&page.title, &page.title)
+            .to_string()
+    });
+
+    let mut res = json!({
+        "result": "Success",
+        "title": page.title,
+        "oldrevid": page.rev_id.unwrap_or(0),
+    });
+    if let Some(id) = page.page_id {
+        res["pageid"] = id.into();
+    }
+    if let Some(model) = &page.content_model {
+        res["contentmodel"] = model.as_str().into();
+    }
+    if !page.exists() {
+        res["new"] = true.into();
+    } else if proposed.as_deref() == Some(current) {
+        res["nochange"] = true.into();
+    }
+    Ok((diff, Some(json!({ "edit": res }))))
+}
+
+/// The error the wiki would refuse the edit with, given the current state of the page.
+fn refusal(edit: &Edit, page: &FetchedPage) -> Option<(ErrorCode, &'static str)> {
+    if edit.createonly && page.exists() {
+        return Some((
+            ErrorCode::ArticleExists,
+            "The article you tried to create has been created already.",
+        ));
+    }
+    if edit.nocreate && !page.exists() {
+        return Some((
+            ErrorCode::MissingTitle,
+            "The page you specified doesn't exist.",
+        ));
+    }
+    let changed_since = |t: MwTimestamp| page.timestamp.is_some_and(|latest| latest > t);
+    let conflict = match (edit.baserevid, edit.basetimestamp) {
+        (Some(base), _) => page.rev_id.is_some_and(|latest| latest != base),
+        (None, Some(base)) => changed_since(base),
+        (None, None) => edit.starttimestamp.is_some_and(changed_since),
+    };
+    conflict.then_some((ErrorCode::EditConflict, "Edit conflict."))
+}
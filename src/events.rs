@@ -8,17 +8,20 @@ use std::pin::Pin;
 use std::task::Poll;
 
 use async_sse::{Decoder, Event};
-use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures_util::stream::{IntoAsyncRead, MapErr, MapOk};
 use futures_util::{Stream, TryStreamExt};
 use serde::Deserialize;
 use serde_json::Value;
 
-type Tr = fn(reqwest::Error) -> io::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::ReqwestTransport;
+use crate::transport::{ByteStream, HttpRequest, Transport};
+use crate::types::Namespace;
+
+type Tr = fn(crate::Error) -> io::Error;
 type TrOk = fn(Event) -> crate::Result<serde_json::Value>;
-type ReqStream = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>>>>;
-type ReqwestSseDecoder = MapOk<Decoder<IntoAsyncRead<MapErr<ReqStream, Tr>>>, TrOk>;
+type ReqwestSseDecoder = MapOk<Decoder<IntoAsyncRead<MapErr<ByteStream, Tr>>>, TrOk>;
 
 pub struct ReqwestSseStream<C> {
     pub decoder: ReqwestSseDecoder,
@@ -50,7 +53,7 @@ pub struct RecentChangeEvent {
     #[serde(rename = "type")]
     pub ty: Option<String>,
     pub title: Option<String>,
-    pub namespace: Option<i64>,
+    pub namespace: Option<Namespace>,
     pub comment: Option<String>,
     pub parsedcomment: Option<String>,
     pub timestamp: Option<i64>,
@@ -77,7 +80,7 @@ pub struct RevisionScoreEvent {
     pub meta: EventMeta,
     pub page_id: NonZeroU64,
     pub page_title: String,
-    pub page_namespace: i64,
+    pub page_namespace: Namespace,
     pub page_is_redirect: bool,
     pub rev_id: u64,
     pub rev_parent_id: Option<u64>,
@@ -112,13 +115,17 @@ impl ReqwestSseStream<RevisionScoreEvent> {
 impl<C> ReqwestSseStream<C> {
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn new(url: &str) -> crate::Result<Self> {
-        let res = reqwest::get(url).await?;
-        let f: Tr = |e| io::Error::new(io::ErrorKind::Other, e);
+        Self::with_transport(&ReqwestTransport::default(), url).await
+    }
+
+    /// Connect to an event stream, sending the request with `transport`.
+    pub async fn with_transport(transport: &dyn Transport, url: &str) -> crate::Result<Self> {
+        let s = transport.stream(HttpRequest::get(url.parse()?)).await?;
+        let f: Tr = |e| io::Error::other(e);
         let o: TrOk = |e| match e {
             Event::Message(m) => Ok(serde_json::from_slice(m.data())?),
             _ => panic!("what?"),
         };
-        let s: ReqStream = Box::pin(res.bytes_stream());
         let decoder = async_sse::decode(s.map_err(f).into_async_read()).map_ok(o);
 
         Ok(Self {
@@ -0,0 +1,96 @@
+//! Bot exclusion with `{{bots}}` and `{{nobots}}`, which pages use to keep some or all bots
+//! away. See <https://en.wikipedia.org/wiki/Template:Bots>.
+//!
+//! | Wikitext | Meaning |
+//! |---|---|
+//! | `{{nobots}}`, `{{bots\|allow=none}}`, `{{bots\|deny=all}}` | No bot may edit |
+//! | `{{bots}}`, `{{bots\|allow=all}}`, `{{bots\|deny=none}}` | Every bot may edit |
+//! | `{{bots\|allow=A,B}}` | Only A and B may edit |
+//! | `{{bots\|deny=A,B}}` | Every bot but A and B may edit |
+//! | `{{bots\|optout=T,U}}`, `{{bots\|optout=all}}` | No bot may leave messages of these types |
+
+/// Whether a bot may edit a page with this text. `message_type` is the kind of message the edit
+/// leaves, such as `afd` or `nosource`, to be checked against `optout`.
+///
+/// Every `{{bots}}` and `{{nobots}}` on the page counts: the bot is kept away if any of them
+/// denies it.
+pub fn allows(text: &str, username: &str, message_type: Option<&str>) -> bool {
+    let username = normalize(username);
+    find_templates(&strip_comments(text))
+        .iter()
+        .all(|(name, params)| template_allows(name, params, &username, message_type))
+}
+
+fn template_allows(
+    name: &str,
+    params: &[(String, String)],
+    username: &str,
+    message_type: Option<&str>,
+) -> bool {
+    let mut allowed = true;
+    for (key, value) in params {
+        let list: Vec<_> = value.split(',').map(normalize).collect();
+        let has = |s: &str| list.iter().any(|x| x == s);
+        match &**key {
+            "allow" if has("all") => return true,
+            "allow" => allowed &= has(username),
+            "deny" if has("none") => {}
+            "deny" if has("all") || has(username) => return false,
+            "optout" => {
+                if let Some(ty) = message_type {
+                    if has("all") || has(&normalize(ty)) {
+                        return false;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    allowed && !(name == "nobots" && params.is_empty())
+}
+
+/// Lowercase with single spaces, so that `Example_Bot` matches `example bot`.
+fn normalize(s: &str) -> String {
+    s.split(|c: char| c == '_' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn strip_comments(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<!--") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find("-->") {
+            Some(end) => rest = &rest[start + end + 3..],
+            None => return out,
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The name, `bots` or `nobots`, and the named parameters of every exclusion template.
+fn find_templates(text: &str) -> Vec<(String, Vec<(String, String)>)> {
+    text.match_indices("{{")
+        .filter_map(|(start, _)| {
+            let inner = &text[start + 2..];
+            let inner = &inner[..inner.find("}}")?];
+            let mut parts = inner.split('|');
+            let name = normalize(parts.next()?);
+            let name = name.strip_prefix("template:").unwrap_or(&name);
+            if name != "bots" && name != "nobots" {
+                return None;
+            }
+            let params = parts
+                .filter_map(|p| {
+                    let (k, v) = p.split_once('=')?;
+                    Some((normalize(k), v.to_owned()))
+                })
+                .collect();
+            Some((name.to_owned(), params))
+        })
+        .collect()
+}
@@ -0,0 +1,247 @@
+//! Many wikis of one farm, such as all Wikimedia projects, listed by `action=sitematrix`.
+//!
+//! ```no_run
+//! # async fn f() -> wiki::Result<()> {
+//! use futures_util::StreamExt;
+//! use wiki::req::Query;
+//!
+//! let farm = wiki::farm::WikiFarm::wikimedia().await?;
+//! let mut results = farm.query_all(["enwiki", "dewiki"], Query::default(), 4);
+//! while let Some((dbname, res)) = results.next().await {
+//!     println!("{dbname}: {} responses", res?.len());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+use serde_json::Value;
+
+use crate::api::RequestBuilderExt;
+use crate::credentials::Secret;
+use crate::oauth::OAuth1Credentials;
+use crate::req::sitematrix::{FarmSiteInfo, SiteMatrix, SiteMatrixResponse};
+use crate::req::{Action, Query};
+use crate::sealed::Access;
+use crate::transport::Transport;
+use crate::{AnonymousAccess, Bot, Client, ClientBuilder, Error, Result};
+
+/// How clients of the farm log in. Wikimedia accounts are global, so one OAuth consumer works
+/// on every wiki.
+#[derive(Clone)]
+enum Identity {
+    OAuth(Secret),
+    OAuth1(OAuth1Credentials),
+}
+
+/// The wikis of a farm by database name, handing out a client for each on demand.
+///
+/// Clients are created on first use and cached, so asking twice for the same wiki is cheap.
+pub struct WikiFarm {
+    sites: BTreeMap<String, FarmSiteInfo>,
+    api_path: String,
+    transport: Option<Arc<dyn Transport>>,
+    identity: Option<Identity>,
+    clients: Mutex<HashMap<String, Client<AnonymousAccess>>>,
+    bots: Mutex<HashMap<String, Bot>>,
+}
+
+impl WikiFarm {
+    /// A farm of these wikis.
+    pub fn new(sites: impl IntoIterator<Item = FarmSiteInfo>) -> Self {
+        Self {
+            sites: sites
+                .into_iter()
+                .map(|site| (site.dbname.clone(), site))
+                .collect(),
+            api_path: "/w/api.php".to_owned(),
+            transport: None,
+            identity: None,
+            clients: Default::default(),
+            bots: Default::default(),
+        }
+    }
+
+    /// Load the site matrix through `client`, which can be any wiki of the farm.
+    pub async fn load<A: Access>(client: &Client<A>) -> Result<Self> {
+        let mut sites = Vec::new();
+        let mut cont = None;
+        loop {
+            let res: SiteMatrixResponse = client
+                .get(Action::SiteMatrix(SiteMatrix {
+                    cont,
+                    ..Default::default()
+                }))
+                .send_parse()
+                .await?;
+            sites.extend(res.sites);
+            match res.cont {
+                Some(c) => cont = Some(c),
+                None => break,
+            }
+        }
+        Ok(Self::new(sites))
+    }
+
+    /// Load the Wikimedia projects from meta.wikimedia.org.
+    pub async fn wikimedia() -> Result<Self> {
+        let meta = ClientBuilder::new("https://meta.wikimedia.org/w/api.php").build()?;
+        Self::load(&meta).await
+    }
+
+    /// The path of `api.php` on each server. Defaults to `/w/api.php`.
+    pub fn api_path(mut self, path: impl Into<String>) -> Self {
+        self.api_path = path.into();
+        self
+    }
+
+    /// Send the requests of every client with this transport.
+    pub fn transport(mut self, transport: impl Transport) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Log in to every wiki with the access token of an owner-only OAuth 2 consumer. See
+    /// [`ClientBuilder::oauth`].
+    pub fn oauth(mut self, token: impl Into<Secret>) -> Self {
+        self.identity = Some(Identity::OAuth(token.into()));
+        self
+    }
+
+    /// Log in to every wiki with an OAuth 1.0a consumer. See [`ClientBuilder::oauth1`].
+    pub fn oauth1(mut self, credentials: OAuth1Credentials) -> Self {
+        self.identity = Some(Identity::OAuth1(credentials));
+        self
+    }
+
+    /// A wiki by its database name, such as `enwiki`.
+    pub fn site(&self, dbname: &str) -> Option<&FarmSiteInfo> {
+        self.sites.get(dbname)
+    }
+
+    /// All wikis, sorted by database name.
+    pub fn sites(&self) -> impl Iterator<Item = &FarmSiteInfo> {
+        self.sites.values()
+    }
+
+    /// The URL of `api.php` of a wiki.
+    pub fn api_url(&self, dbname: &str) -> Option<String> {
+        let site = self.site(dbname)?;
+        Some(format!("{}{}", site.url, self.api_path))
+    }
+
+    /// The database name of the wiki at this URL, which can be the server, `api.php` or any page.
+    pub fn dbname(&self, url: &str) -> Option<&str> {
+        let host = |url: &str| {
+            let url = reqwest::Url::parse(url).ok()?;
+            Some((url.host_str()?.to_owned(), url.port()))
+        };
+        let wanted = host(url)?;
+        self.sites
+            .values()
+            .find(|site| host(&site.url).as_ref() == Some(&wanted))
+            .map(|site| &*site.dbname)
+    }
+
+    fn builder(&self, dbname: &str) -> Result<ClientBuilder<AnonymousAccess>> {
+        let url = self
+            .api_url(dbname)
+            .ok_or_else(|| Error::UnknownWiki(dbname.to_owned()))?;
+        let mut builder = ClientBuilder::new(&url);
+        if let Some(transport) = &self.transport {
+            builder = builder.transport(transport.clone());
+        }
+        Ok(builder)
+    }
+
+    /// A client for a wiki that is not logged in.
+    pub fn client(&self, dbname: &str) -> Result<Client<AnonymousAccess>> {
+        if let Some(client) = self.clients.lock().unwrap().get(dbname) {
+            return Ok(client.clone());
+        }
+        let client = self.builder(dbname)?.build()?;
+        self.clients
+            .lock()
+            .unwrap()
+            .insert(dbname.to_owned(), client.clone());
+        Ok(client)
+    }
+
+    /// A client for a wiki that is logged in with the identity of the farm, set with
+    /// [`WikiFarm::oauth`] or [`WikiFarm::oauth1`].
+    pub async fn bot(&self, dbname: &str) -> Result<Bot> {
+        let cached = self.bots.lock().unwrap().get(dbname).cloned();
+        if let Some(bot) = cached {
+            return Ok(bot);
+        }
+        let builder = self.builder(dbname)?;
+        let bot = match self.identity.clone() {
+            Some(Identity::OAuth(token)) => builder.oauth(token).build().await?,
+            Some(Identity::OAuth1(credentials)) => builder.oauth1(credentials).build().await?,
+            None => {
+                return Err(Error::CustomStatic(
+                    "the farm has no identity to log in with",
+                ))
+            }
+        };
+        self.bots
+            .lock()
+            .unwrap()
+            .insert(dbname.to_owned(), bot.clone());
+        Ok(bot)
+    }
+
+    /// Run `f` with a client for each of these wikis, at most `concurrency` at a time. Results
+    /// are yielded as they complete, with the database name of their wiki.
+    pub fn fan_out<'a, I, F, Fut, T>(
+        &'a self,
+        dbnames: I,
+        concurrency: usize,
+        f: F,
+    ) -> impl Stream<Item = (String, Result<T>)> + 'a
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+        I::IntoIter: 'a,
+        F: Fn(Client<AnonymousAccess>) -> Fut + 'a,
+        Fut: Future<Output = Result<T>> + 'a,
+        T: 'a,
+    {
+        stream::iter(dbnames)
+            .map(move |dbname| {
+                let dbname = dbname.into();
+                let client = self.client(&dbname);
+                let fut = client.map(&f);
+                async move {
+                    let res = match fut {
+                        Ok(fut) => fut.await,
+                        Err(e) => Err(e),
+                    };
+                    (dbname, res)
+                }
+            })
+            .buffer_unordered(concurrency.max(1))
+    }
+
+    /// Run a query with continuation on each of these wikis, at most `concurrency` at a time.
+    pub fn query_all<'a, I>(
+        &'a self,
+        dbnames: I,
+        query: Query,
+        concurrency: usize,
+    ) -> impl Stream<Item = (String, Result<Vec<Value>>)> + 'a
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+        I::IntoIter: 'a,
+    {
+        self.fan_out(dbnames, concurrency, move |client| {
+            let query = query.clone();
+            async move { client.query_all(query).try_collect().await }
+        })
+    }
+}
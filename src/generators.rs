@@ -5,14 +5,13 @@ use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use futures_util::Stream;
-use reqwest::Url;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 use tracing::{trace, trace_span};
 
 use crate::api::{
-    BasicSearchResult, BoxFuture, MaybeContinue, RecentChangesResult, RequestBuilderExt, Revisions,
-    SlotsMain,
+    BasicSearchResult, BoxFuture, MaybeContinue, RecentChangesResult, RequestBuilderExt,
+    RequestMethod,
 };
 use crate::req::rc::ListRc;
 use crate::req::search::{ListSearch, SearchInfo, SearchProp};
@@ -20,9 +19,6 @@ use crate::req::{self, Main, Query, QueryList};
 use crate::sealed::Access;
 use crate::{api, Client};
 
-pub type BoxReqFuture = BoxFuture<reqwest::Result<reqwest::Response>>;
-pub type BoxRecvFuture = BoxFuture<reqwest::Result<api::QueryResponse<Revisions<SlotsMain>>>>;
-
 pub type ResponseFuture<G> =
     BoxFuture<crate::Result<MaybeContinue<<G as WikiGenerator>::Response>>>;
 
@@ -76,24 +72,19 @@ impl<G: WikiGenerator> Stream for GeneratorStream<G> {
             };
         }
 
-        let url = match this.state.as_mut().project() {
+        let req = match this.state.as_mut().project() {
             StateProj::Init => {
                 let main = this.generator.create_request();
                 trace!("created request");
-                let u = crate::api::mkurl(this.generator.url().clone(), main);
-                trace!("created url");
-                u
+                this.generator.site().request(RequestMethod::Get, main)
             }
             StateProj::Cont(v) => {
                 let main = this.generator.create_request();
                 trace!("created request");
-                let u = tryit!(crate::api::mkurl_with_ext(
-                    this.generator.url().clone(),
-                    main,
-                    v.take()
-                ));
-                trace!("created url");
-                u
+                this.generator
+                    .site()
+                    .request(RequestMethod::Get, main)
+                    .ext(v.take())
             }
             StateProj::Values(v, cont) => {
                 let value = v.pop().expect("must always have value");
@@ -120,7 +111,7 @@ impl<G: WikiGenerator> Stream for GeneratorStream<G> {
             StateProj::Done => return Poll::Ready(None),
         };
 
-        let req = this.generator.client().get(url).send_parse();
+        let req = req.send_parse();
         trace!("sent request");
 
         drop(entered);
@@ -131,10 +122,11 @@ impl<G: WikiGenerator> Stream for GeneratorStream<G> {
 }
 
 pub trait WikiGenerator {
+    type Access: Access;
     type Item: 'static;
     type Response: DeserializeOwned;
-    fn url(&self) -> &Url;
-    fn client(&self) -> &reqwest::Client;
+    /// The client requests are sent with.
+    fn site(&self) -> &Client<Self::Access>;
     fn create_request(&self) -> Main;
     fn untangle_response(&self, res: Self::Response) -> crate::Result<Vec<Self::Item>>;
     fn into_stream(self) -> GeneratorStream<Self>
@@ -161,8 +153,8 @@ pub struct GenGen<Access: crate::sealed::Access, State, C, U, Response, Item> {
 impl<A, State, C, U, Response, Item> GenGen<A, State, C, U, Response, Item>
 where
     A: Access,
-    C: Fn(&Url, &reqwest::Client, &State) -> Main,
-    U: Fn(&Url, &reqwest::Client, &State, Response) -> crate::Result<Vec<Item>>,
+    C: Fn(&Client<A>, &State) -> Main,
+    U: Fn(&Client<A>, &State, Response) -> crate::Result<Vec<Item>>,
     Response: DeserializeOwned,
 {
     pub fn new(site: Client<A>, state: State, create_request: C, untangle_response: U) -> Self {
@@ -179,28 +171,25 @@ where
 impl<A, State, C, U, Response, Item> WikiGenerator for GenGen<A, State, C, U, Response, Item>
 where
     A: Access,
-    C: Fn(&Url, &reqwest::Client, &State) -> Main,
-    U: Fn(&Url, &reqwest::Client, &State, Response) -> crate::Result<Vec<Item>>,
+    C: Fn(&Client<A>, &State) -> Main,
+    U: Fn(&Client<A>, &State, Response) -> crate::Result<Vec<Item>>,
     Response: DeserializeOwned,
     Item: 'static,
 {
+    type Access = A;
     type Item = Item;
     type Response = Response;
 
-    fn url(&self) -> &Url {
-        &self.site.url
-    }
-
-    fn client(&self) -> &reqwest::Client {
-        &self.site.client
+    fn site(&self) -> &Client<A> {
+        &self.site
     }
 
     fn create_request(&self) -> Main {
-        (self.create_request)(self.url(), self.client(), &self.state)
+        (self.create_request)(&self.site, &self.state)
     }
 
     fn untangle_response(&self, res: Self::Response) -> crate::Result<Vec<Self::Item>> {
-        (self.untangle_response)(self.url(), self.client(), &self.state, res)
+        (self.untangle_response)(&self.site, &self.state, res)
     }
 }
 
@@ -210,15 +199,12 @@ pub struct SearchGenerator<A: Access> {
 }
 
 impl<A: Access> WikiGenerator for SearchGenerator<A> {
+    type Access = A;
     type Item = BasicSearchResult;
     type Response = api::QueryResponse<api::Search<BasicSearchResult>>;
 
-    fn url(&self) -> &Url {
-        &self.site.url
-    }
-
-    fn client(&self) -> &reqwest::Client {
-        &self.site.client
+    fn site(&self) -> &Client<A> {
+        &self.site
     }
 
     fn create_request(&self) -> Main {
@@ -260,13 +246,11 @@ impl<A: Access> RecentChangesGenerator<A> {
 }
 
 impl<A: Access> WikiGenerator for RecentChangesGenerator<A> {
+    type Access = A;
     type Item = RecentChangesResult;
     type Response = api::QueryResponse<api::RecentChanges<RecentChangesResult>>;
-    fn url(&self) -> &Url {
-        &self.site.url
-    }
-    fn client(&self) -> &reqwest::Client {
-        &self.site.client
+    fn site(&self) -> &Client<A> {
+        &self.site
     }
     fn create_request(&self) -> Main {
         Main::query(Query {
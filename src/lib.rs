@@ -42,21 +42,32 @@
 //! ```
 //!
 
+use std::collections::HashMap;
 use std::fmt;
 use std::marker::PhantomData;
-
-use api::{BoxFuture, CsrfToken, QueryAllGenerator, RequestBuilderExt, Token};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use api::error::ErrorCode;
+use api::{
+    ApiRequest, BoxFuture, CsrfToken, QueryAllGenerator, RequestBuilderExt, RequestMethod, Token,
+};
+use credentials::{redact_url, Secret};
 use deterministic::IsMain;
 use futures_util::future::MapOk;
 use futures_util::TryFutureExt;
 use generators::GeneratorStream;
-use req::{Main, PageSpec, SerializeAdaptor};
+use oauth::{OAuth1Credentials, OAuth2Session};
+use req::{Assert, GlobalParams, Main, PageSpec, TokenType};
 use reqwest::header::InvalidHeaderValue;
 #[cfg(target_arch = "wasm32")]
 use reqwest::header::{HeaderMap, HeaderValue};
-use reqwest::{RequestBuilder, Url};
+use reqwest::Url;
+use retry::RetryPolicy;
 use serde_json::Value;
+use siteinfo::{MetaSiteInfo, SiProp, SiteInfo};
 use tracing::debug;
+use transport::{HttpRequest, ReqwestTransport, Transport};
 
 use crate::generators::WikiGenerator;
 
@@ -65,12 +76,24 @@ extern crate self as wiki;
 pub mod api;
 mod boring_impls;
 mod builder;
+mod cookies;
+pub mod credentials;
 pub mod deterministic;
+pub mod dry_run;
 pub mod events;
+pub mod exclusion;
+pub mod farm;
 pub mod generators;
+pub mod login;
 pub mod macro_support;
+pub mod oauth;
 pub mod req;
 pub mod res;
+pub mod retry;
+pub mod shutoff;
+pub mod siteinfo;
+pub mod title;
+pub mod transport;
 pub mod types;
 pub mod url;
 pub mod util;
@@ -86,23 +109,47 @@ pub struct AnonymousAccess;
 pub struct AuthorizedAccess(());
 
 pub(crate) mod sealed {
-    pub trait Access {}
+    pub trait Access: Send + Sync + 'static {}
     impl Access for super::AnonymousAccess {}
     impl Access for super::AuthorizedAccess {}
 }
 
 /// A generic client for a MediaWiki API endpoint. Could be logged in depending on the type parameter
 pub struct Client<T: sealed::Access = AnonymousAccess> {
-    pub client: reqwest::Client,
+    transport: Arc<dyn Transport>,
     url: Url,
+    retry: RetryPolicy,
+    max_url_len: usize,
+    global: GlobalParams,
+    tokens: Arc<Mutex<HashMap<TokenType, String>>>,
+    site_info: Arc<Mutex<Option<Arc<SiteInfo>>>>,
+    oauth1: Option<Arc<OAuth1Credentials>>,
+    oauth2: Option<Arc<OAuth2Session>>,
+    cookies: cookies::Jar,
+    assert: Option<Assert>,
+    user: Option<String>,
+    shutoff: Option<Arc<shutoff::Shutoff>>,
+    journal: Option<Arc<dry_run::Journal>>,
     acc: PhantomData<T>,
 }
 
 impl<T: sealed::Access> Clone for Client<T> {
     fn clone(&self) -> Self {
         Self {
-            client: self.client.clone(),
+            transport: self.transport.clone(),
             url: self.url.clone(),
+            retry: self.retry.clone(),
+            max_url_len: self.max_url_len,
+            global: self.global.clone(),
+            tokens: self.tokens.clone(),
+            site_info: self.site_info.clone(),
+            oauth1: self.oauth1.clone(),
+            oauth2: self.oauth2.clone(),
+            cookies: self.cookies.clone(),
+            assert: self.assert,
+            user: self.user.clone(),
+            shutoff: self.shutoff.clone(),
+            journal: self.journal.clone(),
             acc: PhantomData,
         }
     }
@@ -111,8 +158,8 @@ impl<T: sealed::Access> Clone for Client<T> {
 impl<T: sealed::Access> fmt::Debug for Client<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Site")
-            .field("client", &self.client)
             .field("url", &self.url)
+            .field("retry", &self.retry)
             .finish()
     }
 }
@@ -133,13 +180,92 @@ pub enum Error {
     #[error(transparent)]
     InvalidHeaderValue(#[from] InvalidHeaderValue),
     #[error("MediaWiki API returned error: {0}")]
-    MediaWiki(serde_json::Value),
+    MediaWiki(api::error::ApiErrors),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("failed to load or save cookies: {0}")]
+    Cookies(Box<dyn std::error::Error + Send + Sync>),
+    #[error("OAuth error: {0}")]
+    OAuth(String),
+    #[error("failed to log in: {0}")]
+    Login(#[from] login::LoginError),
     #[error("failed to log in")]
     Unauthorized,
+    #[error("invalid title: {0}")]
+    InvalidTitle(#[from] title::TitleError),
+    /// The server responded with a status that means the request should be retried later.
+    #[error("HTTP status {0}")]
+    HttpStatus(reqwest::StatusCode),
+    /// A [`Cassette`](transport::cassette::Cassette) being replayed has no response for this
+    /// request.
+    #[error("no recorded response for {0}")]
+    NotRecorded(String),
+    /// A [`WikiFarm`](farm::WikiFarm) has no wiki with this database name.
+    #[error("unknown wiki {0}")]
+    UnknownWiki(String),
+    /// The client is no longer logged in as the user it logged in as, usually because the
+    /// session expired. Nothing was done; log in again and retry.
+    #[error("no longer logged in as asserted: {0}")]
+    AssertFailed(api::error::ApiErrors),
+    #[error("failed to load credentials: {0}")]
+    Credentials(String),
+    /// The shutoff page set with [`ClientBuilder::shutoff`] was changed.
+    #[error("the bot was shut off on {0}")]
+    ShutOff(String),
+    /// The page keeps this bot away with `{{bots}}` or `{{nobots}}`, checked because of
+    /// [`EditBuilder::check_exclusion`](req::EditBuilder::check_exclusion).
+    #[error("{0} excludes this bot with {{{{bots}}}} or {{{{nobots}}}}")]
+    BotExcluded(String),
+    /// The API answered an edit with `result: Failure`, for example to ask for a CAPTCHA, or with
+    /// a result other than `Success`.
+    #[error("edit failed: {}", .0.info.as_deref().or(.0.code.as_deref()).unwrap_or("unknown reason"))]
+    EditFailed(Box<res::EditResult>),
     #[error("{0}")]
     CustomStatic(&'static str),
 }
 
+impl Error {
+    /// The code of the error returned by the API, if this is an API error.
+    pub fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            Self::MediaWiki(e) | Self::AssertFailed(e) => Some(e.code()),
+            _ => None,
+        }
+    }
+
+    /// Whether the request failed because the client is no longer logged in as the user it
+    /// logged in as, usually because the session expired.
+    pub fn is_assert_failed(&self) -> bool {
+        matches!(self, Self::AssertFailed(_))
+    }
+
+    /// Whether this is an API error with the specified code.
+    pub fn is_code(&self, code: &ErrorCode) -> bool {
+        match self {
+            Self::MediaWiki(e) | Self::AssertFailed(e) => e.has_code(code),
+            _ => false,
+        }
+    }
+
+    /// Turn API errors about failed `assert` and `assertuser` checks into
+    /// [`Error::AssertFailed`].
+    pub(crate) fn classify_assert(self) -> Self {
+        const CODES: [ErrorCode; 4] = [
+            ErrorCode::AssertAnonFailed,
+            ErrorCode::AssertUserFailed,
+            ErrorCode::AssertBotFailed,
+            ErrorCode::AssertNamedUserFailed,
+        ];
+        match self {
+            Self::MediaWiki(e) if CODES.iter().any(|c| e.has_code(c)) => Self::AssertFailed(e),
+            e => e,
+        }
+    }
+}
+
+/// How often [`Client::edit_page`] runs its closure again after an edit conflict.
+pub const EDIT_CONFLICT_RETRIES: usize = 3;
+
 /// The result type for this crate.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -163,10 +289,36 @@ impl<A: sealed::Access> Client<A> {
 
     /// Fetches the latest wikitext from a page based on page id or page title.
     pub async fn fetch_content(&self, page: impl Into<PageSpec>) -> Result<String> {
+        self.fetch_page(page)
+            .await?
+            .content
+            .ok_or(Error::CustomStatic("not enough revisions"))
+    }
+
+    /// Fetches the latest revision of a page along with what is needed to detect edit conflicts
+    /// when editing it with [`Client::build_edit_from`].
+    pub async fn fetch_page(&self, page: impl Into<PageSpec>) -> Result<res::FetchedPage> {
+        #[derive(serde::Deserialize)]
+        struct Page {
+            #[serde(rename = "pageid")]
+            page_id: Option<u32>,
+            /// Missing for a page id that does not exist.
+            title: Option<String>,
+            #[serde(default)]
+            revisions: Vec<Revision>,
+        }
+        #[derive(serde::Deserialize)]
+        struct Revision {
+            #[serde(rename = "revid")]
+            rev_id: u64,
+            timestamp: types::MwTimestamp,
+            slots: api::SlotsMain,
+        }
+
         let mut q = req::Query {
             prop: Some(
                 req::QueryProp::Revisions(req::QueryPropRevisions {
-                    prop: req::RvProp::CONTENT,
+                    prop: req::RvProp::CONTENT | req::RvProp::IDS | req::RvProp::TIMESTAMP,
                     slots: req::RvSlot::Main.into(),
                     limit: req::Limit::Value(1),
                 })
@@ -179,20 +331,34 @@ impl<A: sealed::Access> Client<A> {
             PageSpec::PageId(id) => q.pageids = Some(vec![id]),
             PageSpec::Title(title) => q.titles = Some(vec![title]),
         }
-        let x: api::QueryResponse<api::Pages<api::RevisionsList<api::RevisionSlots>>> =
-            self.get(req::Action::Query(q)).send_parse().await?;
-        let page = x
+        let mut main = Main::query(q);
+        main.global.curtimestamp = Some(true);
+        let res: api::Response<api::QueryResponse<api::Pages<Page>>> = self
+            .request(RequestMethod::Get, main)
+            .send_parse_with_warnings()
+            .await?;
+        let page = res
+            .body
             .query
             .pages
             .into_iter()
             .next()
             .ok_or(Error::CustomStatic("not enough pages"))?;
-        let rev = page
-            .revisions
-            .into_iter()
-            .next()
-            .ok_or(Error::CustomStatic("not enough revisions"))?;
-        Ok(rev.slots.main.content)
+        let rev = page.revisions.into_iter().next();
+        Ok(res::FetchedPage {
+            page_id: page.page_id,
+            title: page
+                .title
+                .ok_or(Error::CustomStatic("no page with this id"))?,
+            rev_id: rev.as_ref().map(|r| r.rev_id),
+            timestamp: rev.as_ref().map(|r| r.timestamp),
+            content_model: rev.as_ref().map(|r| r.slots.main.content_model.clone()),
+            content: rev.map(|r| r.slots.main.content),
+            fetched_at: res
+                .meta
+                .curtimestamp
+                .ok_or(Error::CustomStatic("no curtimestamp in response"))?,
+        })
     }
 
     /// Start building an edit.
@@ -204,13 +370,105 @@ impl<A: sealed::Access> Client<A> {
         }
     }
 
+    /// Fetch a page, transform its text with `f` and save the result. `f` gets the current text,
+    /// which is empty if the page does not exist, and returns `None` to leave the page alone.
+    ///
+    /// If someone else edits the page in the meantime, `f` is run again on their text, up to
+    /// [`EDIT_CONFLICT_RETRIES`] times. Returns `None` if `f` skipped the page.
+    pub async fn edit_page<F, T>(
+        &self,
+        page: impl Into<PageSpec>,
+        f: F,
+    ) -> Result<Option<res::EditResult>>
+    where
+        F: FnMut(&str) -> Option<T>,
+        T: Into<req::NewText>,
+    {
+        self.edit_page_retrying(page, EDIT_CONFLICT_RETRIES, f)
+            .await
+    }
+
+    /// [`Client::edit_page`] with a different number of retries after edit conflicts.
+    pub async fn edit_page_retrying<F, T>(
+        &self,
+        page: impl Into<PageSpec>,
+        retries: usize,
+        mut f: F,
+    ) -> Result<Option<res::EditResult>>
+    where
+        F: FnMut(&str) -> Option<T>,
+        T: Into<req::NewText>,
+    {
+        let page = page.into();
+        let mut attempt = 0;
+        loop {
+            let fetched = self.fetch_page(page.clone()).await?;
+            let Some(new) = f(fetched.content.as_deref().unwrap_or_default()) else {
+                return Ok(None);
+            };
+            let req::NewText {
+                text,
+                summary,
+                minor,
+                bot,
+                check_exclusion,
+                message_type,
+            } = new.into();
+            let mut edit = self.build_edit_from(&fetched).text(text);
+            if let Some(rev_id) = fetched.rev_id {
+                edit = edit.baserevid(rev_id);
+            }
+            if let Some(summary) = summary {
+                edit = edit.summary(summary);
+            }
+            if minor {
+                edit = edit.minor();
+            }
+            if bot {
+                edit = edit.bot();
+            }
+            if check_exclusion {
+                edit = edit.check_exclusion();
+            }
+            if let Some(ty) = message_type {
+                edit = edit.message_type(ty);
+            }
+            match edit.send().await {
+                Err(e)
+                    if attempt < retries
+                        && (e.is_code(&ErrorCode::EditConflict)
+                            || e.is_code(&ErrorCode::ArticleExists)) =>
+                {
+                    debug!(?page, attempt, "edit conflict, retrying");
+                    attempt += 1;
+                }
+                res => return res.map(Some),
+            }
+        }
+    }
+
+    /// Start building an edit of a page fetched with [`Client::fetch_page`]. The edit fails with
+    /// [`ErrorCode::EditConflict`] if the page was changed since it was fetched, and with
+    /// `articleexists` or `pagedeleted` if it was created or deleted since.
+    pub fn build_edit_from(&self, page: &res::FetchedPage) -> req::EditBuilder<Self> {
+        let q = self
+            .build_edit(page.title.as_str())
+            .starttimestamp(page.fetched_at)
+            .fetched_text(page.content.as_deref().unwrap_or_default());
+        match page.timestamp {
+            Some(base) => q.basetimestamp(base),
+            None => q.createonly(),
+        }
+    }
+
     /// Build a GET request based on the specific action. This will always use JSON format version 2.
-    pub fn get(&self, action: req::Action) -> RequestBuilder {
-        let url = self.mkurl(Main {
-            action,
-            format: req::Format::Json { formatversion: 2 },
-        });
-        self.client.get(url)
+    pub fn get(&self, action: req::Action) -> ApiRequest<A> {
+        self.request(RequestMethod::Get, Main::action(action))
+    }
+
+    /// Build a request from the full set of parameters.
+    pub fn request(&self, method: RequestMethod, main: Main) -> ApiRequest<A> {
+        ApiRequest::new(self.clone(), method, main)
     }
 
     /// An experimental way for GET requests. Uses const generics to specify the actual request at
@@ -222,19 +480,81 @@ impl<A: sealed::Access> Client<A> {
         }
         let mut url = self.url.clone();
         url.set_query(Some(&q.0));
-        debug!(%url, "GET");
-        Ok(self.client.get(url).send_parse().await?)
+        debug!(url = %redact_url(&url), "GET");
+        let req = self.authorize(HttpRequest::get(url))?;
+        let res = api::execute(&*self.transport, req).await.0?;
+        Ok(serde_json::from_value(res.body)?)
+    }
+
+    /// Add the credentials that are not handled by the cookie store to a request.
+    pub(crate) fn authorize(&self, mut req: HttpRequest) -> Result<HttpRequest> {
+        if let Some(oauth2) = &self.oauth2 {
+            oauth2.authorize(&mut req)?;
+        }
+        if let Some(oauth1) = &self.oauth1 {
+            oauth1.sign(&mut req)?;
+        }
+        Ok(req)
+    }
+
+    /// Get a new OAuth 2 access token if `err` says the one the request was `sent_with` is no
+    /// longer valid. Returns whether the request should be sent again.
+    pub(crate) async fn refresh_oauth2(
+        &self,
+        err: &Error,
+        sent_with: Option<&str>,
+    ) -> Result<bool> {
+        match &self.oauth2 {
+            Some(oauth2)
+                if oauth2.can_refresh() && err.is_code(&ErrorCode::InvalidAuthorization) =>
+            {
+                debug!("OAuth 2 access token expired, refreshing");
+                oauth2.refresh(&*self.transport, sent_with).await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// The transport requests are sent with.
+    pub fn transport(&self) -> &dyn Transport {
+        &*self.transport
+    }
+
+    /// The reqwest client requests are sent with, unless a custom transport was set with
+    /// [`ClientBuilder::transport`] that is not built on reqwest.
+    pub fn reqwest_client(&self) -> Option<&reqwest::Client> {
+        self.transport.reqwest_client()
+    }
+
+    /// The URL of `api.php`.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The name of the user this client is logged in as.
+    pub fn username(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Save the session cookies to a file, to be restored with
+    /// [`ClientBuilder::cookie_file`] by the next run. Does nothing on WebAssembly, where the
+    /// browser manages cookies.
+    pub fn save_cookies(&self, path: impl AsRef<Path>) -> Result<()> {
+        cookies::save(&self.cookies, path.as_ref())
+    }
+
+    /// The current OAuth 2 refresh token, if this client uses
+    /// [`OAuth2Credentials`](oauth::OAuth2Credentials).
+    ///
+    /// Refresh tokens are rotated on every refresh, so save this to be able to log in again later.
+    pub fn oauth2_refresh_token(&self) -> Option<String> {
+        self.oauth2.as_ref().and_then(|o| o.refresh_token())
     }
 
     /// Build a POST request based on the specific action. This will always use JSON format version 2.
-    pub fn post(&self, action: req::Action) -> RequestBuilder {
-        let main = Main {
-            action,
-            format: req::Format::Json { formatversion: 2 },
-        };
-        self.client
-            .post(self.url.clone())
-            .form(&SerializeAdaptor(main))
+    pub fn post(&self, action: req::Action) -> ApiRequest<A> {
+        self.request(RequestMethod::Post, Main::action(action))
     }
 
     /// Retrieve a CSRF token for editing.
@@ -244,46 +564,138 @@ impl<A: sealed::Access> Client<A> {
 
     /// Get a token.
     pub fn get_token<T: Token>(&self) -> TokensFuture<T> {
-        let url = self.mkurl(Main {
-            action: req::Action::Query(req::Query {
-                meta: Some(req::QueryMeta::Tokens { type_: T::types() }.into()),
-                ..Default::default()
-            }),
-            format: req::Format::Json { formatversion: 2 },
-        });
-
-        self.client
-            .get(url)
+        self.request(RequestMethod::Get, Main::tokens(T::types()))
             .send_parse()
             .map_ok(|x: api::QueryResponse<api::Tokens<T>>| x.query.tokens)
     }
 
+    /// Get a token, reusing the one fetched before if there is one.
+    ///
+    /// Tokens stay valid for the whole session, so this saves a request for every write. Use
+    /// [`Client::invalidate_token`] when the API rejects a cached token.
+    pub async fn token<T: Token>(&self) -> Result<T> {
+        if let Some(token) = self.tokens.lock().unwrap().get(&T::types()) {
+            return Ok(T::from_value(token.clone()));
+        }
+        let token: T = self.get_token().await?;
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(T::types(), token.value().to_owned());
+        Ok(token)
+    }
+
+    /// Information about the wiki, fetched on the first call and shared by all clones of the
+    /// client.
+    pub async fn site_info(&self) -> Result<Arc<SiteInfo>> {
+        let cached = self.site_info.lock().unwrap().clone();
+        if let Some(info) = cached {
+            return Ok(info);
+        }
+        let meta = req::QueryMeta::SiteInfo(MetaSiteInfo {
+            prop: SiProp::default(),
+        });
+        let res: api::QueryResponse<SiteInfo> = self
+            .get(req::Action::Query(req::Query {
+                meta: Some(meta.into()),
+                ..Default::default()
+            }))
+            .send_parse()
+            .await?;
+        let info = Arc::new(res.query);
+        *self.site_info.lock().unwrap() = Some(info.clone());
+        Ok(info)
+    }
+
+    /// Parse a title against the namespaces of the wiki.
+    pub async fn title(&self, title: &str) -> Result<title::Title> {
+        let info = self.site_info().await?;
+        Ok(title::Title::new(title, &info)?)
+    }
+
+    /// Remove a token from the cache, so that the next call to [`Client::token`] fetches it again.
+    pub fn invalidate_token<T: Token>(&self) {
+        self.tokens.lock().unwrap().remove(&T::types());
+    }
+
+    /// Fail with [`Error::ShutOff`] if the shutoff page set with [`ClientBuilder::shutoff`] has
+    /// changed. This is done before every write, such as an edit or a block; long-running loops
+    /// can call it to stop before doing any other work.
+    pub async fn check_shutoff(&self) -> Result<()> {
+        match &self.shutoff {
+            Some(shutoff) => shutoff.check(self).await,
+            None => Ok(()),
+        }
+    }
+
+    /// The writes recorded instead of sent by a client built with
+    /// [`ClientBuilder::dry_run`], oldest first. Empty for other clients.
+    pub fn journal(&self) -> Vec<dry_run::JournalEntry> {
+        self.journal
+            .as_ref()
+            .map(|journal| journal.entries())
+            .unwrap_or_default()
+    }
+
+    /// POST an action that requires a token, such as an edit.
+    ///
+    /// `action` receives the cached token. If the API rejects it with `badtoken`, a new token is
+    /// fetched and the action is sent once more.
+    ///
+    /// Clients built with [`ClientBuilder::dry_run`] record the action in the
+    /// [journal](Client::journal) instead, without fetching a token.
+    pub async fn post_with_token<T: Token>(
+        &self,
+        action: impl Fn(String) -> req::Action,
+    ) -> Result<Value> {
+        if self.journal.is_some() {
+            let action = action(dry_run::PLACEHOLDER_TOKEN.to_owned());
+            return self.post(action).send_and_report_err().await;
+        }
+        let token = self.token::<T>().await?;
+        match self
+            .post(action(token.value().to_owned()))
+            .send_and_report_err()
+            .await
+        {
+            Err(e) if e.is_code(&ErrorCode::BadToken) => {
+                debug!("token was rejected, fetching a new one");
+                self.invalidate_token::<T>();
+                let token = self.token::<T>().await?;
+                self.post(action(token.value().to_owned()))
+                    .send_and_report_err()
+                    .await
+            }
+            res => res,
+        }
+    }
+
     /// Perform a query, except returns a `Stream` of results that continues from `continue` parameters
     /// in the responses.
     pub fn query_all(&self, query: req::Query) -> GeneratorStream<QueryAllGenerator<A>> {
         let m = Main::query(query);
 
-        fn clone(_: &Url, _: &reqwest::Client, v: &Main) -> Main {
+        fn clone<A: sealed::Access>(_: &Client<A>, v: &Main) -> Main {
             v.clone()
         }
 
-        fn response(_: &Url, _: &reqwest::Client, _: &Main, v: Value) -> Result<Vec<Value>> {
+        fn response<A: sealed::Access>(_: &Client<A>, _: &Main, v: Value) -> Result<Vec<Value>> {
             Ok(vec![v])
         }
 
-        QueryAllGenerator::new(self.clone(), m, clone, response).into_stream()
+        QueryAllGenerator::new(self.clone(), m, clone::<A>, response::<A>).into_stream()
     }
 }
 
 /// A structure for bot passwords.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct BotPassword {
     username: String,
-    password: String,
+    password: Secret,
 }
 
 impl BotPassword {
-    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+    pub fn new(username: impl Into<String>, password: impl Into<Secret>) -> Self {
         Self {
             username: username.into(),
             password: password.into(),
@@ -291,6 +703,9 @@ impl BotPassword {
     }
 }
 
+/// URLs longer than this are refused by some servers; Wikimedia allows a little over 8 KiB.
+const DEFAULT_MAX_URL_LEN: usize = 8000;
+
 const UA: &str = concat!(
     "wiki.rs",
     "/",
@@ -304,9 +719,10 @@ impl Client {
         let url: Url = api_url.parse()?;
         assert!(url.query().is_none());
         let mut client = reqwest::Client::builder();
+        let cookies = cookies::load(None)?;
         #[cfg(not(target_arch = "wasm32"))]
         {
-            client = client.cookie_store(true).user_agent(UA);
+            client = client.cookie_provider(cookies.clone()).user_agent(UA);
         }
 
         #[cfg(target_arch = "wasm32")]
@@ -316,11 +732,23 @@ impl Client {
             client = client.default_headers(headers);
         }
 
-        let client = client.build()?;
+        let transport = Arc::new(ReqwestTransport::new(client.build()?));
 
         Ok(Client {
-            client,
+            transport,
             url,
+            retry: RetryPolicy::default(),
+            max_url_len: DEFAULT_MAX_URL_LEN,
+            global: GlobalParams::default(),
+            tokens: Default::default(),
+            site_info: Default::default(),
+            oauth1: None,
+            oauth2: None,
+            cookies,
+            assert: None,
+            user: None,
+            shutoff: None,
+            journal: None,
             acc: PhantomData,
         })
     }
@@ -0,0 +1,203 @@
+//! Responses of the login modules.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+/// The `result` of an `action=login` request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoginResult {
+    Success,
+    /// The login token was missing.
+    NeedToken,
+    /// The login token was invalid, usually because the session cookie was lost.
+    WrongToken,
+    /// The username or password was wrong.
+    Failed,
+    /// The login was aborted, either because the main account password was used instead of a bot
+    /// password, or because no code was supplied when `clientlogin` asked for one.
+    Aborted,
+    /// Too many failed attempts. Retry after [`LoginError::wait`] seconds.
+    Throttled,
+    NotExists,
+    WrongPass,
+    EmptyPass,
+    Blocked,
+    /// `clientlogin` wants to redirect to a third-party provider, which is not supported.
+    Redirect,
+    /// `clientlogin` could not link the third-party account and the login has to be restarted.
+    Restart,
+    Other(String),
+}
+
+impl From<String> for LoginResult {
+    fn from(s: String) -> Self {
+        match &*s {
+            "Success" => Self::Success,
+            "NeedToken" => Self::NeedToken,
+            "WrongToken" => Self::WrongToken,
+            "Failed" => Self::Failed,
+            "Aborted" => Self::Aborted,
+            "Throttled" => Self::Throttled,
+            "NotExists" => Self::NotExists,
+            "WrongPass" | "WrongPluginPass" => Self::WrongPass,
+            "EmptyPass" => Self::EmptyPass,
+            "Blocked" => Self::Blocked,
+            _ => Self::Other(s),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LoginResult {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        String::deserialize(d).map(Into::into)
+    }
+}
+
+impl fmt::Display for LoginResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Other(s) => f.write_str(s),
+            _ => fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoginResponse {
+    pub login: LoginInfo,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LoginInfo {
+    pub result: LoginResult,
+    #[serde(default, deserialize_with = "reason")]
+    pub reason: Option<String>,
+    #[serde(rename = "lguserid")]
+    pub user_id: Option<u64>,
+    #[serde(rename = "lgusername")]
+    pub username: Option<String>,
+    pub wait: Option<u64>,
+}
+
+impl LoginInfo {
+    /// Turn anything other than `Success` into an error.
+    pub fn into_result(self) -> Result<Self, LoginError> {
+        if self.result == LoginResult::Success {
+            Ok(self)
+        } else {
+            Err(LoginError {
+                result: self.result,
+                reason: self.reason,
+                wait: self.wait,
+            })
+        }
+    }
+}
+
+/// A login attempt that did not succeed.
+#[derive(Clone, Debug)]
+pub struct LoginError {
+    pub result: LoginResult,
+    /// The reason given by the server, if any.
+    pub reason: Option<String>,
+    /// Seconds to wait before trying again, sent when throttled.
+    pub wait: Option<u64>,
+}
+
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.result)?;
+        if let Some(reason) = &self.reason {
+            write!(f, ": {reason}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LoginError {}
+
+/// The `status` of an `action=clientlogin` request.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ClientLoginStatus {
+    Pass,
+    Fail,
+    /// More information, such as a two-factor code, is required. See [`ClientLoginInfo::requests`].
+    Ui,
+    Redirect,
+    Restart,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ClientLoginResponse {
+    #[serde(rename = "clientlogin")]
+    pub client_login: ClientLoginInfo,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ClientLoginInfo {
+    pub status: ClientLoginStatus,
+    pub username: Option<String>,
+    pub message: Option<String>,
+    #[serde(rename = "messagecode")]
+    pub message_code: Option<String>,
+    #[serde(rename = "redirecttarget")]
+    pub redirect_target: Option<String>,
+    /// The authentication requests that need to be filled in when the status is `UI`.
+    #[serde(default)]
+    pub requests: Vec<AuthRequest>,
+}
+
+/// An authentication request that the server wants the client to fill in.
+#[derive(Deserialize, Debug)]
+pub struct AuthRequest {
+    pub id: String,
+    /// The fields to send, keyed by name, with their description.
+    #[serde(default)]
+    pub fields: HashMap<String, Value>,
+}
+
+impl ClientLoginInfo {
+    /// Whether the server asks for a field with this name, e.g. `OATHToken`.
+    pub fn wants_field(&self, name: &str) -> bool {
+        self.requests.iter().any(|r| r.fields.contains_key(name))
+    }
+
+    /// Turn a status that cannot be continued into an error.
+    pub fn into_error(self) -> LoginError {
+        let result = match self.status {
+            ClientLoginStatus::Pass => LoginResult::Success,
+            ClientLoginStatus::Fail => LoginResult::Failed,
+            ClientLoginStatus::Ui => LoginResult::Aborted,
+            ClientLoginStatus::Redirect => LoginResult::Redirect,
+            ClientLoginStatus::Restart => LoginResult::Restart,
+        };
+        LoginError {
+            result,
+            reason: self.message,
+            wait: None,
+        }
+    }
+}
+
+/// Called when `clientlogin` asks for a two-factor code. Returning `None` aborts the login.
+pub type OathCallback = Box<dyn FnMut(&ClientLoginInfo) -> Option<String> + Send + Sync>;
+
+/// `reason` is a string by default, and an object like an API error when `errorformat` is set.
+fn reason<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<Value>::deserialize(d)? {
+        Some(Value::String(s)) => Some(s),
+        Some(Value::Object(mut o)) => {
+            ["text", "html", "*", "key", "code"]
+                .into_iter()
+                .find_map(|k| match o.remove(k) {
+                    Some(Value::String(s)) => Some(s),
+                    _ => None,
+                })
+        }
+        _ => None,
+    })
+}
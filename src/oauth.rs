@@ -0,0 +1,365 @@
+//! Authenticating with OAuth: signing requests with OAuth 1.0a, and refreshing OAuth 2 tokens.
+
+use std::fmt;
+use std::sync::Mutex;
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+use sha1::Sha1;
+use tracing::debug;
+
+use crate::credentials::Secret;
+use crate::sealed::Access;
+use crate::transport::{Body, HttpRequest, Transport};
+use crate::{Client, Error, Result};
+
+/// Everything except the unreserved characters of RFC 3986 is encoded.
+const ENCODE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+fn enc(s: &str) -> String {
+    utf8_percent_encode(s, ENCODE).to_string()
+}
+
+/// Credentials of an OAuth 1.0a consumer.
+///
+/// For an owner-only consumer, all four values are shown once after proposing it at
+/// <https://meta.wikimedia.org/wiki/Special:OAuthConsumerRegistration/propose/oauth1a>.
+#[derive(Clone)]
+pub struct OAuth1Credentials {
+    pub consumer_key: String,
+    pub consumer_secret: Secret,
+    pub access_token: String,
+    pub access_secret: Secret,
+}
+
+impl fmt::Debug for OAuth1Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth1Credentials")
+            .field("consumer_key", &self.consumer_key)
+            .field("access_token", &self.access_token)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OAuth1Credentials {
+    pub fn new(
+        consumer_key: impl Into<String>,
+        consumer_secret: impl Into<Secret>,
+        access_token: impl Into<String>,
+        access_secret: impl Into<Secret>,
+    ) -> Self {
+        Self {
+            consumer_key: consumer_key.into(),
+            consumer_secret: consumer_secret.into(),
+            access_token: access_token.into(),
+            access_secret: access_secret.into(),
+        }
+    }
+
+    /// Sign a request by adding an `Authorization` header with an HMAC-SHA1 signature.
+    ///
+    /// Query parameters and `application/x-www-form-urlencoded` bodies are covered by the
+    /// signature. Multipart bodies are not, as required by the specification.
+    pub fn sign(&self, req: &mut HttpRequest) -> Result<()> {
+        let nonce: String = rand::thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let header = self.authorization(req, &nonce, &timestamp);
+        let mut header = HeaderValue::from_str(&header)?;
+        header.set_sensitive(true);
+        req.headers.insert(AUTHORIZATION, header);
+        Ok(())
+    }
+
+    pub(crate) fn authorization(&self, req: &HttpRequest, nonce: &str, timestamp: &str) -> String {
+        let mut oauth = vec![
+            ("oauth_consumer_key", self.consumer_key.clone()),
+            ("oauth_nonce", nonce.to_owned()),
+            ("oauth_signature_method", "HMAC-SHA1".to_owned()),
+            ("oauth_timestamp", timestamp.to_owned()),
+            ("oauth_token", self.access_token.clone()),
+            ("oauth_version", "1.0".to_owned()),
+        ];
+
+        let mut params: Vec<_> = req.url.query_pairs().into_owned().collect();
+        if let Body::Form(body) = &req.body {
+            params.extend(::url::form_urlencoded::parse(body.as_bytes()).into_owned());
+        }
+        params.extend(oauth.iter().map(|(k, v)| ((*k).to_owned(), v.clone())));
+        let mut params: Vec<_> = params.iter().map(|(k, v)| (enc(k), enc(v))).collect();
+        params.sort();
+        let params = params
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let mut base_url = req.url.clone();
+        base_url.set_query(None);
+        base_url.set_fragment(None);
+        let base = format!(
+            "{}&{}&{}",
+            req.method.as_str(),
+            enc(base_url.as_str()),
+            enc(&params)
+        );
+        let key = format!(
+            "{}&{}",
+            enc(self.consumer_secret.expose()),
+            enc(self.access_secret.expose())
+        );
+        let mut mac =
+            Hmac::<Sha1>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+        mac.update(base.as_bytes());
+        let signature =
+            base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+        oauth.push(("oauth_signature", signature));
+
+        let fields = oauth
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", enc(v)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("OAuth {fields}")
+    }
+}
+
+/// Credentials of an OAuth 2 client that is not owner-only, used to obtain short-lived access
+/// tokens with a refresh token.
+///
+/// See [`authorize_url`] and [`exchange_code`] for obtaining the first refresh token.
+#[derive(Clone)]
+pub struct OAuth2Credentials {
+    pub client_id: String,
+    pub client_secret: Secret,
+    pub refresh_token: Secret,
+    /// The token endpoint. Defaults to `rest.php/oauth2/access_token` next to `api.php`; see
+    /// [`oauth2_endpoint`].
+    pub token_url: Option<String>,
+}
+
+impl fmt::Debug for OAuth2Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2Credentials")
+            .field("client_id", &self.client_id)
+            .field("token_url", &self.token_url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OAuth2Credentials {
+    pub fn new(
+        client_id: impl Into<String>,
+        client_secret: impl Into<Secret>,
+        refresh_token: impl Into<Secret>,
+    ) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            refresh_token: refresh_token.into(),
+            token_url: None,
+        }
+    }
+
+    pub fn token_url(mut self, url: impl Into<String>) -> Self {
+        self.token_url = Some(url.into());
+        self
+    }
+}
+
+/// A response from the token endpoint.
+#[derive(Deserialize, Clone)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    /// Refresh tokens are rotated, so this replaces the one used to get this token.
+    pub refresh_token: Option<String>,
+    /// Seconds until `access_token` expires.
+    pub expires_in: Option<u64>,
+}
+
+impl fmt::Debug for OAuth2Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth2Token")
+            .field("expires_in", &self.expires_in)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The URL of an OAuth 2 endpoint of the wiki, such as `authorize` or `access_token`.
+///
+/// The OAuth extension serves OAuth 2 only through the REST API, as `rest.php/oauth2/*`; the
+/// `Special:OAuth` pages are the OAuth 1.0a endpoints. `rest.php` is assumed to be next to
+/// `api.php`, as on Wikimedia wikis. Elsewhere, set [`OAuth2Credentials::token_url`].
+pub fn oauth2_endpoint(api_url: &Url, name: &str) -> Result<Url> {
+    Ok(api_url.join(&format!("rest.php/oauth2/{name}"))?)
+}
+
+/// The URL to send users to so they can authorize the client. Once they do, they are redirected
+/// to the callback URL of the client with a `code` to pass to [`exchange_code`].
+pub fn authorize_url(api_url: &Url, client_id: &str, state: &str) -> Result<Url> {
+    let mut url = oauth2_endpoint(api_url, "authorize")?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", client_id)
+        .append_pair("state", state);
+    Ok(url)
+}
+
+/// Exchange the `code` from the authorization callback for an access and refresh token.
+pub async fn exchange_code<A: Access>(
+    client: &Client<A>,
+    client_id: &str,
+    client_secret: &str,
+    code: &str,
+) -> Result<OAuth2Token> {
+    let url = oauth2_endpoint(&client.url, "access_token")?;
+    request_token(
+        client.transport(),
+        url,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+        ],
+    )
+    .await
+}
+
+async fn request_token(
+    transport: &dyn Transport,
+    url: Url,
+    params: &[(&str, &str)],
+) -> Result<OAuth2Token> {
+    debug!(%url, "requesting OAuth 2 token");
+    let body = Body::Form(serde_urlencoded::to_string(params)?);
+    let res = transport.send(HttpRequest::post(url, body)).await?;
+    let v: Value = serde_json::from_slice(&res.body)?;
+    if !res.status.is_success() {
+        let msg = ["message", "error_description", "error"]
+            .into_iter()
+            .find_map(|k| v.get(k).and_then(Value::as_str))
+            .unwrap_or("token request failed");
+        return Err(Error::OAuth(format!("{}: {msg}", res.status)));
+    }
+    Ok(serde_json::from_value(v)?)
+}
+
+/// The OAuth 2 access token of a client. If it was obtained with [`OAuth2Credentials`], it is
+/// refreshed when it expires.
+pub(crate) struct OAuth2Session {
+    refresh: Option<Refresh>,
+    access_token: Mutex<String>,
+    /// Held while refreshing. The refresh token is rotated, so it can only be used once.
+    refreshing: futures_util::lock::Mutex<()>,
+}
+
+struct Refresh {
+    client_id: String,
+    client_secret: Secret,
+    token_url: Url,
+    refresh_token: Mutex<String>,
+}
+
+impl OAuth2Session {
+    /// A fixed access token, such as the one of an owner-only client.
+    pub(crate) fn from_access_token(token: String) -> Self {
+        Self {
+            refresh: None,
+            access_token: Mutex::new(token),
+            refreshing: Default::default(),
+        }
+    }
+
+    pub(crate) fn new(creds: OAuth2Credentials, api_url: &Url) -> Result<Self> {
+        let token_url = match creds.token_url {
+            Some(url) => url.parse()?,
+            None => oauth2_endpoint(api_url, "access_token")?,
+        };
+        Ok(Self {
+            refresh: Some(Refresh {
+                client_id: creds.client_id,
+                client_secret: creds.client_secret,
+                token_url,
+                refresh_token: Mutex::new(creds.refresh_token.expose().to_owned()),
+            }),
+            access_token: Mutex::default(),
+            refreshing: Default::default(),
+        })
+    }
+
+    pub(crate) fn authorize(&self, req: &mut HttpRequest) -> Result<()> {
+        let token = format!("Bearer {}", self.access_token.lock().unwrap());
+        let mut token = HeaderValue::from_str(&token)?;
+        token.set_sensitive(true);
+        req.headers.insert(AUTHORIZATION, token);
+        Ok(())
+    }
+
+    /// The access token a request was sent with.
+    pub(crate) fn sent_with(req: &HttpRequest) -> Option<String> {
+        let header = req.headers.get(AUTHORIZATION)?.to_str().ok()?;
+        Some(header.strip_prefix("Bearer ")?.to_owned())
+    }
+
+    pub(crate) fn can_refresh(&self) -> bool {
+        self.refresh.is_some()
+    }
+
+    pub(crate) fn refresh_token(&self) -> Option<String> {
+        let refresh = self.refresh.as_ref()?;
+        Some(refresh.refresh_token.lock().unwrap().clone())
+    }
+
+    /// Get a new access token with the refresh token, unless the access token is no longer
+    /// `stale` because another request refreshed it in the meantime.
+    pub(crate) async fn refresh(
+        &self,
+        transport: &dyn Transport,
+        stale: Option<&str>,
+    ) -> Result<()> {
+        let Some(refresh) = &self.refresh else {
+            return Ok(());
+        };
+        let _refreshing = self.refreshing.lock().await;
+        if stale.is_some_and(|stale| *self.access_token.lock().unwrap() != stale) {
+            debug!("OAuth 2 access token was already refreshed");
+            return Ok(());
+        }
+        let refresh_token = refresh.refresh_token.lock().unwrap().clone();
+        let token = request_token(
+            transport,
+            refresh.token_url.clone(),
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+                ("client_id", &refresh.client_id),
+                ("client_secret", refresh.client_secret.expose()),
+            ],
+        )
+        .await?;
+        debug!(
+            expires_in = token.expires_in,
+            "refreshed OAuth 2 access token"
+        );
+        *self.access_token.lock().unwrap() = token.access_token;
+        if let Some(refresh_token) = token.refresh_token {
+            *refresh.refresh_token.lock().unwrap() = refresh_token;
+        }
+        Ok(())
+    }
+}
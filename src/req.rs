@@ -6,10 +6,12 @@ use bytemuck::TransparentWrapper;
 use serde::ser::SerializeSeq;
 use wikiproc::WriteUrl;
 
-use crate::api::RequestBuilderExt;
+use crate::api::CsrfToken;
+use crate::exclusion;
 use crate::macro_support::{
     BufferedName, NamedEnum, TriStr, UrlParamWriter, WriteUrlParams, WriteUrlValue,
 };
+use crate::res::{EditResult, EditStatus};
 use crate::types::MwTimestamp;
 use crate::url::{BitflaggedEnum, SerdeAdaptor};
 
@@ -60,19 +62,14 @@ impl<T: Debug> Debug for VariantBased<T> {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum Limit {
     Max,
     Value(usize),
+    #[default]
     None,
 }
 
-impl Default for Limit {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl WriteUrlValue for Limit {
     fn ser<W: UrlParamWriter>(&self, w: BufferedName<'_, W>) -> Result<(), W::E> {
         match self {
@@ -185,6 +182,7 @@ has_value_to_string! {
     i32,
     u32,
     u64,
+    crate::types::Namespace,
 }
 
 pub struct MultiValueEncoder {
@@ -265,13 +263,13 @@ impl<'a, T: BitflaggedEnum + Clone + 'static> From<&'a [T]> for EnumSet<T> {
     }
 }
 
-impl<'a, T: BitflaggedEnum> From<T> for EnumSet<T> {
+impl<T: BitflaggedEnum> From<T> for EnumSet<T> {
     fn from(x: T) -> Self {
         Self::new_one(x)
     }
 }
 
-impl<'a, T: BitflaggedEnum, const LEN: usize> From<[T; LEN]> for EnumSet<T> {
+impl<T: BitflaggedEnum, const LEN: usize> From<[T; LEN]> for EnumSet<T> {
     fn from(arr: [T; LEN]) -> Self {
         let mut flag = Default::default();
         for x in &arr {
@@ -288,6 +286,58 @@ impl<'a, T: BitflaggedEnum, const LEN: usize> From<[T; LEN]> for EnumSet<T> {
 pub struct Main {
     pub action: Action,
     pub format: Format,
+    /// Fail with a `maxlag` error if database replication lag exceeds this many seconds.
+    pub maxlag: Option<u32>,
+    /// Fail if the request is not made as this kind of user.
+    pub assert: Option<Assert>,
+    /// Fail if the request is not made as this user.
+    pub assertuser: Option<String>,
+    #[wp(flatten)]
+    pub global: GlobalParams,
+}
+
+/// Parameters accepted by every module. Defaults for a client can be set with
+/// [`ClientBuilder::global_params`](crate::ClientBuilder::global_params); the response fields
+/// they enable are in [`ResponseMeta`](crate::api::ResponseMeta).
+#[derive(WriteUrl, Clone, Debug, Default, PartialEq, Eq)]
+pub struct GlobalParams {
+    /// The language of messages, such as `en`. Also accepts `user` and `content`.
+    pub uselang: Option<String>,
+    /// The language variant to convert content to, such as `zh-hans`.
+    pub variant: Option<String>,
+    /// How errors and warnings are formatted.
+    pub errorformat: Option<ErrorFormat>,
+    /// Include the current time in the response. `Some(false)` turns off a client-wide default.
+    pub curtimestamp: Option<bool>,
+    /// Include the name of the server that handled the request in the response.
+    pub servedby: Option<bool>,
+    /// Echoed back in the response.
+    pub requestid: Option<String>,
+}
+
+impl GlobalParams {
+    /// Fill in the parameters that are not set from `defaults`.
+    pub fn or(mut self, defaults: &GlobalParams) -> Self {
+        self.uselang = self.uselang.or_else(|| defaults.uselang.clone());
+        self.variant = self.variant.or_else(|| defaults.variant.clone());
+        self.errorformat = self.errorformat.or(defaults.errorformat);
+        self.curtimestamp = self.curtimestamp.or(defaults.curtimestamp);
+        self.servedby = self.servedby.or(defaults.servedby);
+        self.requestid = self.requestid.or_else(|| defaults.requestid.clone());
+        self
+    }
+}
+
+#[derive(WriteUrl, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// The legacy format, with a single error and warnings as text. The default.
+    Bc,
+    Plaintext,
+    Wikitext,
+    Html,
+    /// Message keys and parameters.
+    Raw,
+    None,
 }
 
 impl Main {
@@ -310,6 +360,10 @@ impl Main {
         Self {
             action,
             format: Format::Json { formatversion: 2 },
+            maxlag: None,
+            assert: None,
+            assertuser: None,
+            global: GlobalParams::default(),
         }
     }
 
@@ -331,9 +385,18 @@ pub enum Action {
     Query(Query),
     Edit(Edit),
     Login(Login),
+    ClientLogin(ClientLogin),
     Parse(parse::Parse),
     Block(block::Block),
     AbuseFilterCheckMatch(abuse_filter::CheckMatch),
+    SiteMatrix(sitematrix::SiteMatrix),
+}
+
+impl Action {
+    /// Whether the action changes the wiki, so that sending it twice may do it twice.
+    pub fn is_write(&self) -> bool {
+        matches!(self, Self::Edit(_) | Self::Block(_))
+    }
 }
 
 #[derive(WriteUrl, Default, Clone)]
@@ -360,6 +423,7 @@ pub enum QueryList {
 
 pub mod rc;
 pub mod search;
+pub mod sitematrix;
 
 #[derive(WriteUrl, Clone)]
 pub enum QueryMeta {
@@ -368,6 +432,7 @@ pub enum QueryMeta {
         type_: TokenType,
     },
     UserInfo(MetaUserInfo),
+    SiteInfo(crate::siteinfo::MetaSiteInfo),
 }
 
 // TODO rewrite
@@ -452,6 +517,15 @@ pub enum PageSpec {
     PageId(u32),
 }
 
+/// The kind of user a request has to be made as, checked by the `assert` parameter.
+#[derive(WriteUrl, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assert {
+    Anon,
+    User,
+    /// A user with the `bot` right.
+    Bot,
+}
+
 #[derive(WriteUrl, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Watchlist {
     NoChange,
@@ -471,7 +545,7 @@ pub struct Edit {
     pub minor: bool,
     pub notminor: bool,
     pub bot: bool,
-    pub baserevid: Option<u32>,
+    pub baserevid: Option<u64>,
     pub basetimestamp: Option<MwTimestamp>,
     pub starttimestamp: Option<MwTimestamp>,
     pub recreate: bool,
@@ -503,7 +577,7 @@ pub struct EditBuilder<Bot> {
     minor: bool,
     notminor: bool,
     bot: bool,
-    baserevid: Option<u32>,
+    baserevid: Option<u64>,
     basetimestamp: Option<MwTimestamp>,
     starttimestamp: Option<MwTimestamp>,
     recreate: bool,
@@ -522,6 +596,10 @@ pub struct EditBuilder<Bot> {
     token: Option<String>,
     captchaword: Option<String>,
     captchaid: Option<String>,
+    check_exclusion: bool,
+    message_type: Option<String>,
+    /// The current text of the page, if it was already fetched.
+    fetched_text: Option<String>,
 }
 
 macro_rules! builder_fns {
@@ -587,6 +665,9 @@ impl<A: crate::sealed::Access> EditBuilder<crate::Client<A>> {
             token: None,
             captchaword: None,
             captchaid: None,
+            check_exclusion: false,
+            message_type: None,
+            fetched_text: None,
         }
     }
 
@@ -620,6 +701,9 @@ impl<A: crate::sealed::Access> EditBuilder<crate::Client<A>> {
             token,
             captchaword,
             captchaid,
+            check_exclusion,
+            message_type,
+            fetched_text,
         } = self;
         (
             access__,
@@ -652,20 +736,62 @@ impl<A: crate::sealed::Access> EditBuilder<crate::Client<A>> {
                 token,
                 captchaword,
                 captchaid,
+                check_exclusion,
+                message_type,
+                fetched_text,
             },
         )
     }
 
-    pub async fn send(self) -> crate::Result<()> {
-        // TODO handle errors/conflicts
-        let (access, builder) = self.into_parts();
-        access
-            .post(Action::Edit(
-                builder.token(access.get_csrf_token().await?.token).build(),
-            ))
-            .send_and_report_err()
+    /// Save the edit. Edit conflicts and other API errors are returned as errors, as are
+    /// failures such as CAPTCHAs and results this crate does not know, with
+    /// [`Error::EditFailed`](crate::Error::EditFailed).
+    pub async fn send(self) -> crate::Result<EditResult> {
+        #[derive(serde::Deserialize)]
+        struct Response {
+            edit: serde_json::Value,
+        }
+        let (access, mut builder) = self.into_parts();
+        if builder.check_exclusion {
+            let spec = builder.spec.clone().expect("expected page spec");
+            let (title, text) = match builder.fetched_text.take() {
+                Some(text) => match spec {
+                    PageSpec::Title(title) => (title, text),
+                    PageSpec::PageId(id) => (id.to_string(), text),
+                },
+                None => {
+                    // save against the text that was checked, so that adding `{{nobots}}` in
+                    // the meantime makes the edit conflict
+                    let page = access.fetch_page(spec).await?;
+                    builder.starttimestamp.get_or_insert(page.fetched_at);
+                    if builder.basetimestamp.is_none() {
+                        match page.timestamp {
+                            Some(base) => builder.basetimestamp = Some(base),
+                            None => builder.createonly |= !builder.nocreate,
+                        }
+                    }
+                    (page.title, page.content.unwrap_or_default())
+                }
+            };
+            let username = access.username().unwrap_or_default();
+            if !exclusion::allows(&text, username, builder.message_type.as_deref()) {
+                return Err(crate::Error::BotExcluded(title));
+            }
+        }
+        let res = access
+            .post_with_token::<CsrfToken>(|token| {
+                Action::Edit(builder.clone().token(token).build())
+            })
             .await?;
-        Ok(())
+        let Response { edit: raw } = serde_json::from_value(res)?;
+        let mut edit: EditResult = serde_json::from_value(raw.clone())?;
+        edit.raw = raw;
+        match edit.result {
+            EditStatus::Success => Ok(edit),
+            EditStatus::Failure | EditStatus::Unknown => {
+                Err(crate::Error::EditFailed(Box::new(edit)))
+            }
+        }
     }
 }
 
@@ -711,6 +837,9 @@ impl<T> EditBuilder<T> {
                 token: Some(token),
                 captchaword,
                 captchaid,
+                check_exclusion: _,
+                message_type: _,
+                fetched_text: _,
             } => Some(Edit {
                 spec,
                 section,
@@ -754,6 +883,32 @@ impl<T> EditBuilder<T> {
         self
     }
 
+    /// Refuse to save with [`Error::BotExcluded`](crate::Error::BotExcluded) if the page keeps
+    /// this bot away with `{{bots}}` or `{{nobots}}`. See [`exclusion`].
+    ///
+    /// The page is fetched for the check unless the edit was started with
+    /// [`Client::build_edit_from`](crate::Client::build_edit_from). Either way, the edit conflicts
+    /// if the page is changed before it is saved.
+    pub fn check_exclusion(mut self) -> Self {
+        self.check_exclusion = true;
+        self
+    }
+
+    /// The kind of message this edit leaves, for pages that opt out of some with
+    /// `{{bots|optout=...}}`. Implies [`EditBuilder::check_exclusion`].
+    pub fn message_type(mut self, ty: impl Into<String>) -> Self {
+        self.check_exclusion = true;
+        self.message_type = Some(ty.into());
+        self
+    }
+
+    /// The current text of the page, so that [`EditBuilder::check_exclusion`] does not fetch it
+    /// again.
+    pub(crate) fn fetched_text(mut self, text: impl Into<String>) -> Self {
+        self.fetched_text = Some(text.into());
+        self
+    }
+
     pub fn new_section(mut self, title: String) -> Self {
         self.section = Some(EditSection::New { title });
         self
@@ -776,7 +931,7 @@ impl<T> EditBuilder<T> {
         minor: bool,
         notminor: bool,
         bot: bool,
-        baserevid: Option<u32>,
+        baserevid: Option<u64>,
         basetimestamp: Option<MwTimestamp>,
         starttimestamp: Option<MwTimestamp>,
         recreate: bool,
@@ -798,6 +953,70 @@ impl<T> EditBuilder<T> {
     }
 }
 
+/// The text to save in place of the current text of a page, returned from the closure of
+/// [`Client::edit_page`](crate::Client::edit_page).
+#[derive(Clone, Debug, Default)]
+pub struct NewText {
+    pub text: String,
+    pub summary: Option<String>,
+    pub minor: bool,
+    pub bot: bool,
+    /// See [`EditBuilder::check_exclusion`].
+    pub check_exclusion: bool,
+    /// See [`EditBuilder::message_type`].
+    pub message_type: Option<String>,
+}
+
+impl NewText {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary = Some(summary.into());
+        self
+    }
+
+    pub fn minor(mut self) -> Self {
+        self.minor = true;
+        self
+    }
+
+    pub fn bot(mut self) -> Self {
+        self.bot = true;
+        self
+    }
+
+    /// Skip the page with [`Error::BotExcluded`](crate::Error::BotExcluded) if it keeps this
+    /// bot away. See [`EditBuilder::check_exclusion`].
+    pub fn check_exclusion(mut self) -> Self {
+        self.check_exclusion = true;
+        self
+    }
+
+    /// See [`EditBuilder::message_type`].
+    pub fn message_type(mut self, ty: impl Into<String>) -> Self {
+        self.check_exclusion = true;
+        self.message_type = Some(ty.into());
+        self
+    }
+}
+
+impl From<String> for NewText {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<&str> for NewText {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
 #[derive(WriteUrl, Clone)]
 #[wp(prepend_all = "lg")]
 pub struct Login {
@@ -806,6 +1025,18 @@ pub struct Login {
     pub token: String,
 }
 
+/// `action=clientlogin`. The credentials, such as `username`, `password` or `OATHToken`, are
+/// dynamic and have to be sent alongside this.
+#[derive(WriteUrl, Clone)]
+#[wp(prepend_all = "login")]
+pub struct ClientLogin {
+    /// Required for the first request, must not be set when continuing.
+    pub returnurl: Option<String>,
+    #[wp(name = "logincontinue")]
+    pub cont: bool,
+    pub token: String,
+}
+
 #[derive(WriteUrl, Clone, Copy)]
 pub enum Format {
     Json { formatversion: u8 },
@@ -1,6 +1,6 @@
 use wikiproc::WriteUrl;
 
-use crate::types::MwTimestamp;
+use crate::types::{MwTimestamp, Namespace};
 use crate::url::{BufferedName, TriStr, UrlParamWriter, WriteUrlValue};
 
 #[derive(Clone)]
@@ -38,5 +38,5 @@ pub struct Block {
     pub partial: bool,
     pub pagerestrictions: Option<Vec<String>>,
     #[wp(name = "namespacerestrictions")]
-    pub namespace_restrictions: Option<Vec<i32>>,
+    pub namespace_restrictions: Option<Vec<Namespace>>,
 }
@@ -3,6 +3,7 @@ use wikiproc::{bitflags, WriteUrl};
 
 use super::{Limit, PageSpec};
 use crate::build_response_type;
+use crate::types::Namespace;
 
 #[derive(WriteUrl, Clone, Debug)]
 #[wp(prepend_all = "cm")]
@@ -13,6 +14,7 @@ pub struct ListCategoryMembers {
     #[wp(name = "type")]
     pub ty: CategoryMembersType,
     pub prop: CategoryMembersProp,
+    pub namespace: Option<Vec<Namespace>>,
 }
 
 bitflags! {
@@ -39,7 +41,7 @@ bitflags! {
 #[derive(Deserialize, Debug, Clone)]
 pub struct CategoryMember {
     pub pageid: Option<u32>,
-    pub ns: Option<i64>,
+    pub ns: Option<Namespace>,
     pub title: Option<String>,
     pub sortkey: Option<String>,
     pub sortkeyprefix: Option<String>,
@@ -1,6 +1,7 @@
 use wikiproc::WriteUrl;
 
 use super::Limit;
+use crate::types::Namespace;
 
 #[derive(WriteUrl, Clone, Debug)]
 #[wp(prepend_all = "uc")]
@@ -9,6 +10,7 @@ pub struct ListUserContribs {
     #[wp(flatten)]
     pub selector: Selector,
     pub prop: UserContribsProp,
+    pub namespace: Option<Vec<Namespace>>,
 }
 
 #[derive(WriteUrl, Clone, Debug)]
@@ -1,7 +1,7 @@
 use wikiproc::WriteUrl;
 
 use super::Limit;
-use crate::types::NowableTime;
+use crate::types::{Namespace, NowableTime};
 
 #[derive(WriteUrl, Clone)]
 #[wp(prepend_all = "rc")]
@@ -11,6 +11,7 @@ pub struct ListRc {
     pub limit: Limit,
     pub prop: RcProp,
     pub ty: RcType,
+    pub namespace: Option<Vec<Namespace>>,
 }
 
 #[rustfmt::skip]
@@ -3,6 +3,7 @@ use std::num::NonZeroU32;
 use wikiproc::{bitflags, WriteUrl};
 
 use super::Limit;
+use crate::types::Namespace;
 
 #[derive(WriteUrl, Clone)]
 #[wp(prepend_all = "sr")]
@@ -11,7 +12,7 @@ pub struct ListSearch {
     pub limit: Limit,
     pub prop: SearchProp,
     pub info: SearchInfo,
-    pub namespace: Option<String>, // TODO enumfy
+    pub namespace: Option<Vec<Namespace>>,
 }
 
 #[derive(WriteUrl, Clone)]
@@ -22,7 +23,7 @@ pub struct SearchGenerator {
     pub offset: Option<NonZeroU32>,
     pub prop: SearchProp,
     pub info: SearchInfo,
-    pub namespace: Option<String>, // TODO enumfy
+    pub namespace: Option<Vec<Namespace>>,
 }
 
 bitflags! {
@@ -0,0 +1,131 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use wikiproc::{bitflags, WriteUrl};
+
+use super::Limit;
+
+/// `action=sitematrix`, the wikis of a farm such as Wikimedia, grouped by language.
+#[derive(WriteUrl, Clone, Debug)]
+#[wp(prepend_all = "sm")]
+pub struct SiteMatrix {
+    #[wp(name = "type")]
+    pub ty: SiteMatrixType,
+    pub siteprop: SiteMatrixSiteProp,
+    pub limit: Limit,
+    #[wp(name = "continue")]
+    pub cont: Option<String>,
+}
+
+impl Default for SiteMatrix {
+    /// Every wiki, with everything [`FarmSiteInfo`] has fields for.
+    fn default() -> Self {
+        Self {
+            ty: SiteMatrixType::SPECIAL | SiteMatrixType::LANGUAGE,
+            siteprop: SiteMatrixSiteProp::URL
+                | SiteMatrixSiteProp::DBNAME
+                | SiteMatrixSiteProp::CODE
+                | SiteMatrixSiteProp::LANG
+                | SiteMatrixSiteProp::SITENAME,
+            limit: Limit::Max,
+            cont: None,
+        }
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct SiteMatrixType: u8 {
+        const SPECIAL = 1 << 0;
+        const LANGUAGE = 1 << 1;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct SiteMatrixSiteProp: u8 {
+        const URL = 1 << 0;
+        const DBNAME = 1 << 1;
+        const CODE = 1 << 2;
+        const LANG = 1 << 3;
+        const SITENAME = 1 << 4;
+    }
+}
+
+/// A wiki listed by `action=sitematrix`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FarmSiteInfo {
+    pub dbname: String,
+    /// The server, such as `https://en.wikipedia.org`.
+    pub url: String,
+    /// The project, such as `wiki` or `wiktionary`, or the name of a special wiki.
+    pub code: String,
+    /// The language code. Filled in from the language group for language wikis.
+    pub lang: Option<String>,
+    pub sitename: String,
+    #[serde(default)]
+    pub closed: bool,
+    #[serde(default)]
+    pub private: bool,
+    #[serde(default)]
+    pub fishbowl: bool,
+}
+
+#[derive(Deserialize)]
+struct LanguageGroup {
+    code: String,
+    #[serde(default)]
+    site: Vec<FarmSiteInfo>,
+}
+
+/// The response to [`SiteMatrix`].
+#[derive(Debug, Clone)]
+pub struct SiteMatrixResponse {
+    /// Language wikis first, then special wikis, in the order of the response.
+    pub sites: Vec<FarmSiteInfo>,
+    /// The value of `smcontinue` for the next request, if the list is incomplete.
+    pub cont: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for SiteMatrixResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Raw {
+            sitematrix: Map<String, Value>,
+            #[serde(rename = "continue")]
+            cont: Option<Map<String, Value>>,
+        }
+        let Raw {
+            mut sitematrix,
+            cont,
+        } = Raw::deserialize(deserializer)?;
+        fn de<T: DeserializeOwned, E: serde::de::Error>(v: Value) -> Result<T, E> {
+            serde_json::from_value(v).map_err(E::custom)
+        }
+
+        let specials: Vec<FarmSiteInfo> = match sitematrix.remove("specials") {
+            Some(v) => de(v)?,
+            None => Vec::new(),
+        };
+        // the other keys are the language groups, numbered from 0, and `count`
+        let mut groups: Vec<(u32, Value)> = sitematrix
+            .into_iter()
+            .filter_map(|(key, v)| Some((key.parse().ok()?, v)))
+            .collect();
+        groups.sort_by_key(|(n, _)| *n);
+        let mut sites = Vec::new();
+        for (_, v) in groups {
+            let group: LanguageGroup = de(v)?;
+            sites.extend(group.site.into_iter().map(|mut site| {
+                site.lang.get_or_insert_with(|| group.code.clone());
+                site
+            }));
+        }
+        sites.extend(specials);
+
+        let cont = cont
+            .and_then(|mut c| c.remove("smcontinue"))
+            .and_then(|v| v.as_str().map(ToOwned::to_owned));
+        Ok(Self { sites, cont })
+    }
+}
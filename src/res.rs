@@ -1,5 +1,7 @@
 use serde::Deserialize;
 
+use crate::types::{MwTimestamp, Namespace};
+
 #[derive(Deserialize)]
 pub struct QueryResponse<PageExt> {
     pub pages: Option<Vec<PageResponse<PageExt>>>,
@@ -9,8 +11,129 @@ pub struct QueryResponse<PageExt> {
 pub struct PageResponse<Ext> {
     #[serde(rename = "pageid")]
     pub page_id: u64,
-    pub ns: i64,
+    pub ns: Namespace,
     pub title: String,
     #[serde(flatten)]
     pub ext: Ext,
 }
+
+/// The latest revision of a page, fetched with [`Client::fetch_page`](crate::Client::fetch_page)
+/// to be edited.
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    /// `None` if the page does not exist.
+    pub page_id: Option<u32>,
+    pub title: String,
+    pub rev_id: Option<u64>,
+    /// When the latest revision was saved, used as `basetimestamp`.
+    pub timestamp: Option<MwTimestamp>,
+    /// The text of the latest revision, or `None` if the page does not exist.
+    pub content: Option<String>,
+    pub content_model: Option<String>,
+    /// The time of the server when the page was fetched, used as `starttimestamp`.
+    pub fetched_at: MwTimestamp,
+}
+
+impl FetchedPage {
+    pub fn exists(&self) -> bool {
+        self.page_id.is_some()
+    }
+}
+
+/// The `edit` object of a response to `action=edit`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EditResult {
+    pub result: EditStatus,
+    #[serde(rename = "pageid")]
+    pub page_id: Option<u32>,
+    pub title: Option<String>,
+    #[serde(rename = "contentmodel")]
+    pub content_model: Option<String>,
+    /// `0` for a new page.
+    #[serde(rename = "oldrevid")]
+    pub old_rev_id: Option<u64>,
+    #[serde(rename = "newrevid")]
+    pub new_rev_id: Option<u64>,
+    #[serde(rename = "newtimestamp")]
+    pub new_timestamp: Option<MwTimestamp>,
+    #[serde(default)]
+    pub new: bool,
+    #[serde(default, rename = "nochange")]
+    pub no_change: bool,
+    #[serde(default)]
+    pub watched: bool,
+    /// The CAPTCHA to solve before trying again with `captchaid` and `captchaword`.
+    pub captcha: Option<Captcha>,
+    /// The abuse filter that stopped the edit, on wikis that report it here instead of as an
+    /// error.
+    #[serde(rename = "abusefilter")]
+    pub abuse_filter: Option<AbuseFilterHit>,
+    pub code: Option<String>,
+    pub info: Option<String>,
+    /// The `edit` object as received, for what is not parsed above.
+    #[serde(skip)]
+    pub raw: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditStatus {
+    Success,
+    Failure,
+    /// A result this crate does not know, such as one added by an extension. Look at
+    /// [`EditResult::raw`] for the details.
+    #[serde(other)]
+    Unknown,
+}
+
+/// What happened to an edit, summarizing an [`EditResult`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditOutcome {
+    Created,
+    Saved,
+    /// The text was already the same, so no revision was saved.
+    NoChange,
+    Captcha,
+    AbuseFilter,
+    Failure,
+}
+
+impl EditResult {
+    pub fn outcome(&self) -> EditOutcome {
+        match self.result {
+            EditStatus::Success if self.no_change => EditOutcome::NoChange,
+            EditStatus::Success if self.new => EditOutcome::Created,
+            EditStatus::Success => EditOutcome::Saved,
+            EditStatus::Failure if self.captcha.is_some() => EditOutcome::Captcha,
+            EditStatus::Failure if self.abuse_filter.is_some() => EditOutcome::AbuseFilter,
+            EditStatus::Failure | EditStatus::Unknown => EditOutcome::Failure,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Captcha {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub id: String,
+    /// The question for a question CAPTCHA.
+    pub question: Option<String>,
+    /// The image to solve for an image CAPTCHA, relative to the server.
+    pub url: Option<String>,
+    pub mime: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AbuseFilterHit {
+    pub id: FilterId,
+    pub description: String,
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+/// The id of a local filter, or a string such as `global-12` for a global filter.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum FilterId {
+    Local(u64),
+    Other(String),
+}
@@ -0,0 +1,167 @@
+//! Retrying requests that failed because of server load or network trouble.
+
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::api::error::ErrorCode;
+use crate::Error;
+
+/// How requests are retried when the API or the network fails transiently.
+///
+/// A request is retried when:
+///
+/// * the API returned a `maxlag` error,
+/// * the server answered with `429 Too Many Requests`, `502`, `503` or `504`,
+/// * the connection could not be established, timed out, or was reset.
+///
+/// Writes such as edits may have been carried out even though the request failed, so they are
+/// only retried after `maxlag`, `429` and `503`, where the server did nothing.
+///
+/// The delay between retries starts at `initial_backoff` and doubles after every attempt. A
+/// `Retry-After` header sent by the server replaces the doubled delay. Either way, the delay is
+/// capped at `max_backoff`.
+///
+/// To set the `maxlag` parameter used on Wikimedia wikis, use [`RetryPolicy::maxlag`] or
+/// [`ClientBuilder::maxlag`](crate::ClientBuilder::maxlag).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The `maxlag` value sent with every request, in seconds.
+    pub maxlag: Option<u32>,
+    /// How many times a request is retried after the first attempt.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            maxlag: None,
+            max_retries: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub fn maxlag(mut self, maxlag: u32) -> Self {
+        self.maxlag = Some(maxlag);
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Whether the error is worth retrying at all.
+    pub fn is_transient(err: &Error) -> bool {
+        match err {
+            Error::MediaWiki(e) => e.has_code(&ErrorCode::MaxLag),
+            Error::HttpStatus(status) => is_transient_status(*status),
+            Error::Request(e) => {
+                e.is_connect()
+                    || e.is_timeout()
+                    || e.is_request()
+                    || e.is_body()
+                    || e.status().is_some_and(is_transient_status)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a write can be sent again after this error, because the server refused it
+    /// without doing anything.
+    pub fn is_safe_to_resend(err: &Error) -> bool {
+        let refused = |status: StatusCode| {
+            matches!(
+                status,
+                StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+            )
+        };
+        match err {
+            Error::MediaWiki(e) => e.has_code(&ErrorCode::MaxLag),
+            Error::HttpStatus(status) => refused(*status),
+            Error::Request(e) => e.status().is_some_and(refused),
+            _ => false,
+        }
+    }
+
+    /// How long to wait before retrying, or `None` if the request should not be retried.
+    ///
+    /// `attempt` is the number of retries done so far.
+    pub fn delay(
+        &self,
+        attempt: u32,
+        err: &Error,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if !Self::is_transient(err) {
+            return None;
+        }
+        self.backoff_delay(attempt, retry_after)
+    }
+
+    /// [`RetryPolicy::delay`] for a write, which is only retried if
+    /// [`RetryPolicy::is_safe_to_resend`].
+    pub fn write_delay(
+        &self,
+        attempt: u32,
+        err: &Error,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if !Self::is_safe_to_resend(err) {
+            return None;
+        }
+        self.backoff_delay(attempt, retry_after)
+    }
+
+    fn backoff_delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt));
+        Some(retry_after.unwrap_or(backoff).min(self.max_backoff))
+    }
+}
+
+pub(crate) fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Wait before the next attempt. Returns `false` if we cannot wait on this platform, in which
+/// case the request is not retried.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(d: Duration) -> bool {
+    tokio::time::sleep(d).await;
+    true
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(_: Duration) -> bool {
+    false
+}
@@ -0,0 +1,98 @@
+//! Emergency shutoff pages, which let anyone stop a bot by editing its run page.
+//!
+//! ```no_run
+//! # async fn f() -> wiki::Result<()> {
+//! use std::time::Duration;
+//! use wiki::shutoff::ShutoffPage;
+//!
+//! let bot = wiki::ClientBuilder::enwiki()
+//!     .credentials_from_env()?
+//!     .shutoff(ShutoffPage::new("User:DeadbeefBot/Run", "true").interval(Duration::from_secs(60)))
+//!     .build()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tracing::warn;
+
+use crate::{sealed, Client, Error, Result};
+
+/// A page whose content has to stay `expected` for the bot to keep writing. Set with
+/// [`ClientBuilder::shutoff`](crate::ClientBuilder::shutoff).
+#[derive(Clone, Debug)]
+pub struct ShutoffPage {
+    pub page: String,
+    pub expected: String,
+    /// How long to trust the last check. Zero checks before every write.
+    pub interval: Duration,
+}
+
+impl ShutoffPage {
+    pub fn new(page: impl Into<String>, expected: impl Into<String>) -> Self {
+        Self {
+            page: page.into(),
+            expected: expected.into(),
+            interval: Duration::ZERO,
+        }
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+/// A shutoff page with when it was last checked, shared by all clones of a client.
+pub(crate) struct Shutoff {
+    page: ShutoffPage,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    checked_at: Option<DateTime<Utc>>,
+    /// Once shut off, the bot stays off until it is restarted.
+    tripped: bool,
+}
+
+impl Shutoff {
+    pub(crate) fn new(page: ShutoffPage) -> Self {
+        Self {
+            page,
+            state: Default::default(),
+        }
+    }
+
+    pub(crate) async fn check<A: sealed::Access>(&self, client: &Client<A>) -> Result<()> {
+        let now = Utc::now();
+        {
+            let state = self.state.lock().unwrap();
+            if state.tripped {
+                return Err(Error::ShutOff(self.page.page.clone()));
+            }
+            let fresh = state.checked_at.is_some_and(|t| {
+                (now - t)
+                    .to_std()
+                    .is_ok_and(|elapsed| elapsed < self.page.interval)
+            });
+            if fresh {
+                return Ok(());
+            }
+        }
+        // a missing page counts as changed
+        let content = client.fetch_page(self.page.page.as_str()).await?.content;
+        let mut state = self.state.lock().unwrap();
+        if content.as_deref().map(str::trim) != Some(self.page.expected.trim()) {
+            warn!(page = %self.page.page, "shutoff page changed, refusing to write");
+            state.tripped = true;
+            return Err(Error::ShutOff(self.page.page.clone()));
+        }
+        state.checked_at = Some(now);
+        Ok(())
+    }
+}
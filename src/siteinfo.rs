@@ -0,0 +1,224 @@
+//! What the API reports about a wiki with `meta=siteinfo`: its namespaces, interwiki prefixes,
+//! magic words, extensions and URLs.
+//!
+//! Use [`Client::site_info`](crate::Client::site_info) to get it; it is fetched once and cached.
+
+use std::collections::BTreeMap;
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::Url;
+use serde::Deserialize;
+use wikiproc::WriteUrl;
+
+use crate::types::Namespace;
+use crate::Result;
+
+#[derive(WriteUrl, Clone, Debug)]
+#[wp(prepend_all = "si")]
+pub struct MetaSiteInfo {
+    pub prop: SiProp,
+}
+
+wikiproc::bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct SiProp: u16 {
+        const GENERAL = 1 << 0;
+        const NAMESPACES = 1 << 1;
+        const NAMESPACE_ALIASES = 1 << 2;
+        const INTERWIKI_MAP = 1 << 3;
+        const EXTENSIONS = 1 << 4;
+        const MAGIC_WORDS = 1 << 5;
+    }
+}
+
+impl Default for SiProp {
+    /// Everything [`SiteInfo`] has fields for.
+    fn default() -> Self {
+        Self::GENERAL
+            | Self::NAMESPACES
+            | Self::NAMESPACE_ALIASES
+            | Self::INTERWIKI_MAP
+            | Self::EXTENSIONS
+            | Self::MAGIC_WORDS
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SiteInfo {
+    pub general: General,
+    /// Namespaces by id.
+    #[serde(default)]
+    pub namespaces: BTreeMap<Namespace, NamespaceInfo>,
+    #[serde(default, rename = "namespacealiases")]
+    pub namespace_aliases: Vec<NamespaceAlias>,
+    #[serde(default, rename = "interwikimap")]
+    pub interwiki_map: Vec<Interwiki>,
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
+    #[serde(default, rename = "magicwords")]
+    pub magic_words: Vec<MagicWord>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct General {
+    #[serde(rename = "mainpage")]
+    pub main_page: String,
+    #[serde(rename = "sitename")]
+    pub site_name: String,
+    #[serde(rename = "wikiid")]
+    pub wiki_id: String,
+    /// The content language, such as `en`.
+    pub lang: String,
+    /// `first-letter` if the first letter of titles is always uppercase, or `case-sensitive`.
+    pub case: String,
+    /// The server, which may be protocol-relative, such as `//en.wikipedia.org`.
+    pub server: String,
+    /// The path of articles, with `$1` in place of the title, such as `/wiki/$1`.
+    #[serde(rename = "articlepath")]
+    pub article_path: String,
+    /// The path of `index.php`, such as `/w/index.php`.
+    pub script: String,
+    #[serde(rename = "scriptpath")]
+    pub script_path: String,
+    pub generator: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NamespaceInfo {
+    pub id: Namespace,
+    /// The local name, such as `Wikipédia` on French Wikipedia. Empty for the main namespace.
+    pub name: String,
+    /// The canonical English name, such as `Project`. Missing for the main namespace.
+    pub canonical: Option<String>,
+    pub case: String,
+    #[serde(default)]
+    pub subpages: bool,
+    #[serde(default)]
+    pub content: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct NamespaceAlias {
+    pub id: Namespace,
+    pub alias: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Interwiki {
+    pub prefix: String,
+    /// The URL of pages, with `$1` in place of the title.
+    pub url: String,
+    /// Whether the prefix points to another wiki of the same farm.
+    #[serde(default)]
+    pub local: bool,
+    pub language: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Extension {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MagicWord {
+    pub name: String,
+    pub aliases: Vec<String>,
+    #[serde(rename = "case-sensitive", default)]
+    pub case_sensitive: bool,
+}
+
+/// The characters `wfUrlencode` leaves alone in titles.
+const TITLE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b';')
+    .remove(b':')
+    .remove(b'@')
+    .remove(b'$')
+    .remove(b'!')
+    .remove(b'*')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b',')
+    .remove(b'/');
+
+impl SiteInfo {
+    /// Whether the first letter of titles is case-sensitive.
+    pub fn is_case_sensitive(&self) -> bool {
+        self.general.case == "case-sensitive"
+    }
+
+    /// Look up a namespace by its local or canonical name, or an alias, ignoring case and
+    /// treating underscores as spaces.
+    pub fn namespace_by_name(&self, name: &str) -> Option<&NamespaceInfo> {
+        let name = name.replace('_', " ").to_lowercase();
+        let is = |n: &str| n.to_lowercase() == name;
+        self.namespaces
+            .values()
+            .find(|ns| is(&ns.name) || ns.canonical.as_deref().is_some_and(is))
+            .or_else(|| {
+                let alias = self.namespace_aliases.iter().find(|a| is(&a.alias))?;
+                self.namespaces.get(&alias.id)
+            })
+    }
+
+    pub fn interwiki(&self, prefix: &str) -> Option<&Interwiki> {
+        self.interwiki_map
+            .iter()
+            .find(|i| i.prefix.eq_ignore_ascii_case(prefix))
+    }
+
+    /// The aliases of a magic word, such as `#REDIRECT` for `redirect`.
+    pub fn magic_word(&self, name: &str) -> Option<&MagicWord> {
+        self.magic_words.iter().find(|m| m.name == name)
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|e| e.name == name)
+    }
+
+    /// The server with a protocol, defaulting to HTTPS for a protocol-relative server.
+    fn server(&self) -> String {
+        match self.general.server.strip_prefix("//") {
+            Some(host) => format!("https://{host}"),
+            None => self.general.server.clone(),
+        }
+    }
+
+    /// The URL of `index.php` with these parameters.
+    pub fn index_url(&self, params: &[(&str, &str)]) -> Result<Url> {
+        let mut url = Url::parse(&format!("{}{}", self.server(), self.general.script))?;
+        url.query_pairs_mut().extend_pairs(params);
+        Ok(url)
+    }
+
+    /// The URL of a page, such as `https://en.wikipedia.org/wiki/Main_Page`.
+    pub fn page_url(&self, title: &str) -> Result<Url> {
+        let title = title.trim().replace(' ', "_");
+        let title = utf8_percent_encode(&title, TITLE).to_string();
+        let path = self.general.article_path.replace("$1", &title);
+        Ok(Url::parse(&format!("{}{path}", self.server()))?)
+    }
+
+    /// The URL of the difference between two revisions.
+    pub fn diff_url(&self, old_id: u64, new_id: u64) -> Result<Url> {
+        self.index_url(&[
+            ("diff", &new_id.to_string()),
+            ("oldid", &old_id.to_string()),
+        ])
+    }
+
+    /// The URL of a log, such as `block`, optionally only the entries about one page.
+    pub fn log_url(&self, log_type: &str, page: Option<&str>) -> Result<Url> {
+        let mut params = vec![("title", "Special:Log"), ("type", log_type)];
+        if let Some(page) = page {
+            params.push(("page", page));
+        }
+        self.index_url(&params)
+    }
+}
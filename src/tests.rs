@@ -1,2 +1,15 @@
+pub mod cassette;
+pub mod cookies;
+pub mod credentials;
+pub mod edit;
+pub mod error;
+pub mod exclusion;
+pub mod farm;
+pub mod login;
+pub mod oauth;
+pub mod retry;
+pub mod siteinfo;
+pub mod title;
+pub mod transport;
 pub mod types;
 pub mod url;
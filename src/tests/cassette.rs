@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bytes::Bytes;
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+use crate::api::{BoxFuture, RequestBuilderExt};
+use crate::req::{Action, EditBuilder};
+use crate::transport::cassette::{request_key, Cassette};
+use crate::transport::{HttpRequest, HttpResponse, StatusCode, Transport};
+use crate::ClientBuilder;
+
+/// Answers every request with a counter, so that replayed responses can be told apart.
+#[derive(Default)]
+struct Counter(AtomicUsize);
+
+impl Transport for Counter {
+    fn send(&self, _: HttpRequest) -> BoxFuture<crate::Result<HttpResponse>> {
+        let n = self.0.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move {
+            Ok(HttpResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: Bytes::from(format!(r#"{{"n":{n}}}"#)),
+            })
+        })
+    }
+}
+
+fn edit(token: &str) -> Action {
+    Action::Edit(
+        EditBuilder::new()
+            .title("Sandbox")
+            .text("hi")
+            .token(token)
+            .build(),
+    )
+}
+
+#[tokio::test]
+async fn record_then_replay() {
+    let path = std::env::temp_dir().join(format!("wiki-cassette-{}.json", std::process::id()));
+    let url = "https://example.org/w/api.php";
+
+    let client = ClientBuilder::new(url)
+        .transport(Cassette::record(&path, Counter::default()))
+        .build()
+        .unwrap();
+    let v: Value = client.post(edit("abc+\\")).send_parse().await.unwrap();
+    assert_eq!(v["n"], 0);
+    let v: Value = client.post(edit("abc+\\")).send_parse().await.unwrap();
+    assert_eq!(v["n"], 1);
+    drop(client);
+
+    let client = ClientBuilder::new(url)
+        .transport(Cassette::replay(&path).unwrap())
+        .build()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // a different token still matches, in the order recorded.
+    let v: Value = client.post(edit("def+\\")).send_parse().await.unwrap();
+    assert_eq!(v["n"], 0);
+    let v: Value = client.post(edit("def+\\")).send_parse().await.unwrap();
+    assert_eq!(v["n"], 1);
+    let e = client.post(edit("def+\\")).send_parse::<Value>().await;
+    assert!(matches!(e, Err(crate::Error::NotRecorded(_))));
+}
+
+#[test]
+fn key_ignores_tokens() {
+    let url = "https://example.org/w/api.php?type=csrf&meta=tokens&action=query&curtimestamp=";
+    let key = request_key(&HttpRequest::get(url.parse().unwrap()));
+    assert_eq!(
+        key,
+        "GET https://example.org/w/api.php?action=query&meta=tokens&type=csrf"
+    );
+}
+
+/// Answers with the tokens of a session.
+struct Tokens;
+
+impl Transport for Tokens {
+    fn send(&self, _: HttpRequest) -> BoxFuture<crate::Result<HttpResponse>> {
+        let body = r#"{"batchcomplete":true,"query":{"tokens":{"csrftoken":"0123abcd+\\"}},"oauth":[{"refresh_token":"r1"}]}"#;
+        Box::pin(async move {
+            Ok(HttpResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: Bytes::from(body),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn tokens_are_redacted() {
+    let path =
+        std::env::temp_dir().join(format!("wiki-cassette-tokens-{}.json", std::process::id()));
+    let url = "https://example.org/w/api.php?action=query&meta=tokens";
+
+    let cassette = Cassette::record(&path, Tokens);
+    let res = cassette
+        .send(HttpRequest::get(url.parse().unwrap()))
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&res.body).contains("0123abcd"));
+    drop(cassette);
+
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(!saved.contains("0123abcd"));
+    assert!(!saved.contains("r1"));
+
+    let cassette = Cassette::replay(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let res = cassette
+        .send(HttpRequest::get(url.parse().unwrap()))
+        .await
+        .unwrap();
+    let v: Value = serde_json::from_slice(&res.body).unwrap();
+    assert_eq!(v["query"]["tokens"]["csrftoken"], "[redacted]");
+    assert_eq!(v["batchcomplete"], true);
+}
@@ -0,0 +1,26 @@
+use crate::cookies;
+
+#[test]
+fn session_cookies_roundtrip() {
+    let path = std::env::temp_dir().join(format!("wiki-cookies-{}.json", std::process::id()));
+    let url = "https://en.wikipedia.org/w/api.php".parse().unwrap();
+
+    let jar = cookies::load(Some(&path)).unwrap();
+    jar.lock()
+        .unwrap()
+        .parse("enwikiSession=abc; path=/; secure; HttpOnly", &url)
+        .unwrap();
+    cookies::save(&jar, &path).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    let jar = cookies::load(Some(&path)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let store = jar.lock().unwrap();
+    let cookie = store.get("en.wikipedia.org", "/", "enwikiSession").unwrap();
+    assert_eq!(cookie.value(), "abc");
+}
@@ -0,0 +1,72 @@
+use std::fs;
+
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+
+use crate::credentials::{redact_url, Credentials, Secret};
+use crate::transport::{Body, HttpRequest};
+
+#[test]
+fn secrets_are_not_printed() {
+    let secret = Secret::from("hunter2");
+    assert_eq!(secret.expose(), "hunter2");
+    assert!(!format!("{secret:?}").contains("hunter2"));
+
+    let creds = Credentials::OAuth1(crate::oauth::OAuth1Credentials::new(
+        "key", "hunter2", "token", "hunter3",
+    ));
+    assert!(!format!("{creds:?}").contains("hunter"));
+
+    let url =
+        "https://example.org/w/api.php?action=login&lgname=A&lgpassword=hunter2&lgtoken=abc%2B%5C"
+            .parse()
+            .unwrap();
+    assert_eq!(
+        redact_url(&url).as_str(),
+        "https://example.org/w/api.php?action=login&lgname=A&lgpassword=%5Bredacted%5D&lgtoken=%5Bredacted%5D"
+    );
+
+    let mut req = HttpRequest::post(
+        url,
+        Body::Form("action=edit&text=hi&token=abc%2B%5C".to_owned()),
+    );
+    req.headers
+        .insert(AUTHORIZATION, HeaderValue::from_static("Bearer hunter4"));
+    let debug = format!("{req:?}");
+    for secret in ["hunter2", "hunter4", "abc"] {
+        assert!(!debug.contains(secret), "{debug}");
+    }
+    assert!(debug.contains("\"text\", \"hi\""), "{debug}");
+}
+
+#[test]
+fn load() {
+    let dir = std::env::temp_dir().join(format!("wiki-credentials-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let file = dir.join("bot.conf");
+    fs::write(
+        &file,
+        "# bot password\nusername = Example@bot\npassword = hunter2 \n",
+    )
+    .unwrap();
+    match Credentials::from_file(&file).unwrap() {
+        Credentials::BotPassword(pass) => assert_eq!(pass.password.expose(), "hunter2"),
+        c => panic!("{c:?}"),
+    }
+    fs::write(&file, "client_id = abc\nclient_secret = def\n").unwrap();
+    let e = Credentials::from_file(&file).unwrap_err();
+    assert!(e.to_string().contains("refresh_token is missing"), "{e}");
+
+    fs::remove_file(&file).unwrap();
+    fs::write(dir.join("oauth_token"), "hunter2\n").unwrap();
+    match Credentials::from_dir(&dir).unwrap() {
+        Credentials::OAuth(token) => assert_eq!(token.expose(), "hunter2"),
+        c => panic!("{c:?}"),
+    }
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(
+        Credentials::from_env_prefixed("WIKI_RS_TEST_UNSET_"),
+        Err(crate::Error::Credentials(_))
+    ));
+}
@@ -0,0 +1,33 @@
+use serde_json::json;
+
+use crate::res::{EditOutcome, EditResult, FilterId};
+
+#[test]
+fn captcha_and_abuse_filter() {
+    let res: EditResult = serde_json::from_value(json!({
+        "result": "Failure",
+        "captcha": {
+            "type": "image",
+            "mime": "image/png",
+            "id": "1234567",
+            "url": "/w/index.php?title=Special:Captcha/image&wpCaptchaId=1234567"
+        }
+    }))
+    .unwrap();
+    assert_eq!(res.outcome(), EditOutcome::Captcha);
+    assert_eq!(res.captcha.unwrap().id, "1234567");
+
+    let res: EditResult = serde_json::from_value(json!({
+        "result": "Failure",
+        "code": "abusefilter-disallowed",
+        "info": "This action has been automatically identified as harmful.",
+        "abusefilter": {
+            "id": 12,
+            "description": "Page blanking",
+            "actions": ["disallow"]
+        }
+    }))
+    .unwrap();
+    assert_eq!(res.outcome(), EditOutcome::AbuseFilter);
+    assert_eq!(res.abuse_filter.unwrap().id, FilterId::Local(12));
+}
@@ -0,0 +1,88 @@
+use serde_json::json;
+
+use crate::api::error::{ApiErrors, ErrorCode};
+use crate::api::warning::ApiWarning;
+
+#[test]
+fn bc_format() {
+    let mut v = json!({
+        "error": {
+            "code": "maxlag",
+            "info": "Waiting for 10.64.48.35: 7 seconds lagged.",
+            "host": "10.64.48.35",
+            "lag": 7,
+            "type": "db",
+            "docref": "See https://en.wikipedia.org/w/api.php for API usage."
+        },
+        "servedby": "mw1234"
+    });
+    let errors = ApiErrors::from_response(&mut v).unwrap();
+    let e = errors.first();
+    assert_eq!(e.code, ErrorCode::MaxLag);
+    assert_eq!(e.info, "Waiting for 10.64.48.35: 7 seconds lagged.");
+    assert!(e.docref.is_some());
+    assert_eq!(e.module, None);
+    assert_eq!(e.data.as_ref().unwrap()["lag"], 7);
+    assert!(e.data.as_ref().unwrap().get("code").is_none());
+}
+
+#[test]
+fn errorformat() {
+    let mut v = json!({
+        "errors": [
+            {
+                "code": "badtoken",
+                "text": "Invalid CSRF token.",
+                "module": "main"
+            },
+            {
+                "code": "some-extension-error",
+                "html": "<b>oops</b>",
+                "data": { "x": 1 },
+                "module": "edit"
+            }
+        ],
+        "docref": "See https://en.wikipedia.org/w/api.php for API usage."
+    });
+    let errors = ApiErrors::from_response(&mut v).unwrap();
+    assert_eq!(errors.code(), &ErrorCode::BadToken);
+    assert!(errors.has_code(&"some-extension-error".into()));
+    let e = errors.iter().nth(1).unwrap();
+    assert_eq!(e.info, "<b>oops</b>");
+    assert_eq!(e.module.as_deref(), Some("edit"));
+    assert_eq!(e.data, Some(json!({ "x": 1 })));
+    assert!(e.docref.is_some());
+}
+
+#[test]
+fn no_errors() {
+    let mut v = json!({ "batchcomplete": true, "query": {} });
+    assert!(ApiErrors::from_response(&mut v).is_none());
+}
+
+#[test]
+fn warnings() {
+    let mut v = json!({
+        "warnings": {
+            "main": { "warnings": "Unrecognized parameter: rvprop2." },
+            "revisions": { "warnings": "Because \"rvslots\" was not specified...\nSecond line." }
+        },
+        "query": {}
+    });
+    let w = ApiWarning::from_response(&mut v);
+    assert_eq!(w.len(), 3);
+    assert_eq!(w[0].module, "main");
+    assert_eq!(w[2].text, "Second line.");
+    assert!(v.get("warnings").is_none());
+
+    let mut v = json!({
+        "warnings": [{
+            "code": "truncatedresult",
+            "text": "This result was truncated because it would otherwise be larger than the limit of 12,582,912 bytes.",
+            "module": "query"
+        }]
+    });
+    let w = ApiWarning::from_response(&mut v);
+    assert_eq!(w[0].code.as_deref(), Some("truncatedresult"));
+    assert_eq!(w[0].module, "query");
+}
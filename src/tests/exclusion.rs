@@ -0,0 +1,43 @@
+use crate::exclusion::allows;
+
+#[test]
+fn bots_and_nobots() {
+    let bot = "Example bot";
+    assert!(allows("Hello", bot, None));
+    assert!(allows("{{bots}}", bot, None));
+    assert!(!allows("{{nobots}}", bot, None));
+    assert!(!allows("{{Nobots}}", bot, None));
+    assert!(allows("<!-- {{nobots}} -->", bot, None));
+    assert!(!allows("{{bots|allow=none}}", bot, None));
+    assert!(allows("{{bots|allow=all}}", bot, None));
+    assert!(allows("{{bots|allow=Other,Example_bot}}", bot, None));
+    assert!(!allows("{{bots|allow=Other}}", bot, None));
+    assert!(!allows("{{bots|deny=all}}", bot, None));
+    assert!(allows("{{bots|deny=none}}", bot, None));
+    assert!(!allows("{{bots|deny=Other, example bot}}", bot, None));
+    assert!(allows("{{bots|deny=Other}}", bot, None));
+    assert!(allows("{{nobots|allow=Example bot}}", bot, None));
+    // any template can deny
+    assert!(!allows("{{bots}} {{nobots}}", bot, None));
+    assert!(!allows(
+        "{{bots}}\n\nText\n\n{{bots|deny=Example bot}}",
+        bot,
+        None
+    ));
+    assert!(!allows(
+        "{{bots|allow=all}} {{bots|allow=Other}}",
+        bot,
+        None
+    ));
+    assert!(allows("{{bots}} {{bots|deny=Other}}", bot, None));
+}
+
+#[test]
+fn optout() {
+    let bot = "Example bot";
+    assert!(allows("{{bots|optout=afd}}", bot, None));
+    assert!(!allows("{{bots|optout=afd}}", bot, Some("afd")));
+    assert!(allows("{{bots|optout=afd}}", bot, Some("nosource")));
+    assert!(!allows("{{bots|optout=all}}", bot, Some("nosource")));
+    assert!(!allows("{{bots}} {{bots|optout=afd}}", bot, Some("afd")));
+}
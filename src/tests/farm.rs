@@ -0,0 +1,54 @@
+use serde_json::json;
+
+use crate::farm::WikiFarm;
+use crate::req::sitematrix::SiteMatrixResponse;
+
+#[test]
+fn site_matrix() {
+    let site = |dbname: &str, url: &str| json!({ "url": url, "dbname": dbname, "code": "wiki", "sitename": "Wikipedia" });
+    let res: SiteMatrixResponse = serde_json::from_value(json!({
+        "continue": { "smcontinue": "11", "continue": "-||" },
+        "sitematrix": {
+            "count": 4,
+            "10": { "code": "de", "name": "Deutsch", "site": [site("dewiki", "https://de.wikipedia.org")] },
+            "2": { "code": "en", "name": "English", "site": [site("enwiki", "https://en.wikipedia.org")] },
+            "specials": [
+                {
+                    "url": "https://commons.wikimedia.org",
+                    "dbname": "commonswiki",
+                    "code": "commons",
+                    "lang": "commons",
+                    "sitename": "Wikimedia Commons"
+                },
+                {
+                    "url": "https://ten.wikipedia.org",
+                    "dbname": "tenwiki",
+                    "code": "ten",
+                    "lang": "ten",
+                    "sitename": "Wikipedia",
+                    "closed": true
+                }
+            ]
+        }
+    }))
+    .unwrap();
+    assert_eq!(res.cont.as_deref(), Some("11"));
+    let dbnames: Vec<_> = res.sites.iter().map(|s| &*s.dbname).collect();
+    assert_eq!(dbnames, ["enwiki", "dewiki", "commonswiki", "tenwiki"]);
+    assert_eq!(res.sites[1].lang.as_deref(), Some("de"));
+    assert!(res.sites[3].closed);
+
+    let farm = WikiFarm::new(res.sites);
+    assert_eq!(
+        farm.api_url("commonswiki").unwrap(),
+        "https://commons.wikimedia.org/w/api.php"
+    );
+    assert_eq!(
+        farm.dbname("https://de.wikipedia.org/wiki/Berlin"),
+        Some("dewiki")
+    );
+    assert!(matches!(
+        farm.client("frwiki"),
+        Err(crate::Error::UnknownWiki(db)) if db == "frwiki"
+    ));
+}
@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use serde_json::{json, Value};
+
+use crate::api::BoxFuture;
+use crate::login::{ClientLoginResponse, ClientLoginStatus, LoginResponse, LoginResult};
+use crate::transport::{Body, HttpRequest, HttpResponse, StatusCode, Transport};
+use crate::{BotPassword, ClientBuilder, Error};
+
+#[test]
+fn login_failed() {
+    let res: LoginResponse = serde_json::from_value(json!({
+        "login": {
+            "result": "Failed",
+            "reason": "Incorrect username or password entered. Please try again."
+        }
+    }))
+    .unwrap();
+    let e = res.login.into_result().unwrap_err();
+    assert_eq!(e.result, LoginResult::Failed);
+    assert_eq!(
+        e.to_string(),
+        "Failed: Incorrect username or password entered. Please try again."
+    );
+}
+
+#[test]
+fn login_success() {
+    let res: LoginResponse = serde_json::from_value(json!({
+        "login": { "result": "Success", "lguserid": 42, "lgusername": "Example" }
+    }))
+    .unwrap();
+    let info = res.login.into_result().unwrap();
+    assert_eq!(info.user_id, Some(42));
+    assert_eq!(info.username.as_deref(), Some("Example"));
+
+    let res: LoginResponse = serde_json::from_value(json!({
+        "login": { "result": "Throttled", "wait": 300 }
+    }))
+    .unwrap();
+    let e = res.login.into_result().unwrap_err();
+    assert_eq!(e.result, LoginResult::Throttled);
+    assert_eq!(e.wait, Some(300));
+}
+
+#[test]
+fn client_login_ui() {
+    let res: ClientLoginResponse = serde_json::from_value(json!({
+        "clientlogin": {
+            "status": "UI",
+            "message": "Enter a verification code from your authenticator app.",
+            "messagecode": "oathauth-auth-ui",
+            "requests": [{
+                "id": "MediaWiki\\Extension\\OATHAuth\\Auth\\TOTPAuthenticationRequest",
+                "fields": { "OATHToken": { "type": "string", "label": "Token" } }
+            }]
+        }
+    }))
+    .unwrap();
+    let info = res.client_login;
+    assert_eq!(info.status, ClientLoginStatus::Ui);
+    assert!(info.wants_field("OATHToken"));
+    assert!(!info.wants_field("captchaWord"));
+    assert_eq!(info.into_error().result, LoginResult::Aborted);
+}
+
+#[tokio::test]
+async fn oath_without_client_login() {
+    let e = ClientBuilder::new("https://example.org/w/api.php")
+        .password(BotPassword::new("Example@bot", "hunter2"))
+        .oath(|_| Some("123456".into()))
+        .build()
+        .await
+        .unwrap_err();
+    assert!(matches!(e, Error::CustomStatic(_)));
+}
+
+/// Answers with canned responses in order, recording the requests.
+#[derive(Default)]
+struct Canned {
+    requests: Mutex<Vec<HttpRequest>>,
+    responses: Mutex<VecDeque<Value>>,
+}
+
+impl Transport for Canned {
+    fn send(&self, req: HttpRequest) -> BoxFuture<crate::Result<HttpResponse>> {
+        self.requests.lock().unwrap().push(req);
+        let body = self.responses.lock().unwrap().pop_front().unwrap();
+        Box::pin(async move {
+            Ok(HttpResponse {
+                status: StatusCode::OK,
+                headers: Default::default(),
+                body: Bytes::from(body.to_string()),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn client_login_with_oath() {
+    let canned = Arc::new(Canned::default());
+    canned.responses.lock().unwrap().extend([
+        json!({ "query": { "tokens": { "logintoken": "abc+\\" } } }),
+        json!({ "clientlogin": {
+            "status": "UI",
+            "message": "Enter a verification code from your authenticator app.",
+            "requests": [{ "id": "TOTP", "fields": { "OATHToken": { "type": "string" } } }]
+        } }),
+        json!({ "clientlogin": { "status": "PASS", "username": "Example" } }),
+        json!({ "query": { "userinfo": { "id": 1, "name": "Example" } } }),
+    ]);
+    let client = ClientBuilder::new("https://example.org/w/api.php")
+        .transport(canned.clone())
+        .client_login("Example", "hunter2")
+        .oath(|_| Some("123456".into()))
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(client.username(), Some("Example"));
+
+    let requests = canned.requests.lock().unwrap();
+    let Body::Form(body) = &requests[2].body else {
+        panic!("expected a form body");
+    };
+    assert!(body.contains("OATHToken=123456"));
+    assert!(body.contains("logincontinue"));
+}
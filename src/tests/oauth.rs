@@ -0,0 +1,130 @@
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, AUTHORIZATION};
+use reqwest::Url;
+use serde_json::{json, Value};
+
+use crate::api::{BoxFuture, RequestBuilderExt};
+use crate::oauth::{authorize_url, oauth2_endpoint, OAuth1Credentials, OAuth2Credentials};
+use crate::req::{Action, Query};
+use crate::transport::{Body, HttpRequest, HttpResponse, StatusCode, Transport};
+use crate::ClientBuilder;
+
+/// The example from <https://developer.x.com/en/docs/authentication/oauth-1-0a/creating-a-signature>.
+#[test]
+fn signature() {
+    let creds = OAuth1Credentials::new(
+        "xvz1evFS4wEEPTGEFPHBog",
+        "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw",
+        "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb",
+        "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE",
+    );
+    let req = HttpRequest::post(
+        "https://api.twitter.com/1.1/statuses/update.json?include_entities=true"
+            .parse()
+            .unwrap(),
+        Body::Form(
+            "status=Hello%20Ladies%20%2B%20Gentlemen%2C%20a%20signed%20OAuth%20request%21".into(),
+        ),
+    );
+    let header = creds.authorization(
+        &req,
+        "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg",
+        "1318622958",
+    );
+    assert!(header.starts_with("OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\", "));
+    assert!(header.ends_with("oauth_signature=\"hCtSmYh%2BiHYCEqBWrE7C7hYmtUk%3D\""));
+}
+
+#[test]
+fn oauth2_urls() {
+    let api: Url = "https://meta.wikimedia.org/w/api.php".parse().unwrap();
+    assert_eq!(
+        oauth2_endpoint(&api, "access_token").unwrap().as_str(),
+        "https://meta.wikimedia.org/w/rest.php/oauth2/access_token"
+    );
+    assert_eq!(
+        authorize_url(&api, "abc123", "xyz").unwrap().as_str(),
+        "https://meta.wikimedia.org/w/rest.php/oauth2/authorize?response_type=code&client_id=abc123&state=xyz"
+    );
+}
+
+/// An OAuth 2 server that rotates the refresh token on every refresh, and an API that only
+/// accepts the latest access token.
+#[derive(Default)]
+struct Rotating {
+    generation: Mutex<u32>,
+    /// Whether the access token of the current generation is still valid.
+    valid: Mutex<bool>,
+    refreshes: Mutex<u32>,
+}
+
+impl Transport for Rotating {
+    fn send(&self, req: HttpRequest) -> BoxFuture<crate::Result<HttpResponse>> {
+        let mut generation = self.generation.lock().unwrap();
+        let (status, body) = if req.url.path().ends_with("/access_token") {
+            *self.refreshes.lock().unwrap() += 1;
+            let Body::Form(body) = &req.body else {
+                panic!("expected a form body");
+            };
+            let expected = format!("refresh_token=refresh{generation}&");
+            if body.contains(&expected) {
+                *generation += 1;
+                *self.valid.lock().unwrap() = true;
+                let token = json!({
+                    "access_token": format!("access{generation}"),
+                    "refresh_token": format!("refresh{generation}"),
+                    "expires_in": 3600,
+                });
+                (StatusCode::OK, token)
+            } else {
+                let error = json!({ "error": "invalid_request", "message": "bad refresh token" });
+                (StatusCode::BAD_REQUEST, error)
+            }
+        } else if *self.valid.lock().unwrap()
+            && req.headers[AUTHORIZATION] == format!("Bearer access{generation}").as_str()
+        {
+            let info = json!({ "query": { "userinfo": { "id": 1, "name": "Example" } } });
+            (StatusCode::OK, info)
+        } else {
+            let error = json!({
+                "error": { "code": "mwoauth-invalid-authorization", "info": "expired" },
+            });
+            (StatusCode::OK, error)
+        };
+        Box::pin(async move {
+            // let the other request in, so that both fail before either refreshes
+            tokio::task::yield_now().await;
+            Ok(HttpResponse {
+                status,
+                headers: HeaderMap::new(),
+                body: Bytes::from(body.to_string()),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn concurrent_refresh() {
+    let server = Arc::new(Rotating::default());
+    let client = ClientBuilder::new("https://example.org/w/api.php")
+        .transport(server.clone())
+        .oauth2(OAuth2Credentials::new("id", "secret", "refresh0"))
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(*server.refreshes.lock().unwrap(), 1);
+
+    // the access token expires
+    *server.valid.lock().unwrap() = false;
+    let send = || {
+        client
+            .get(Action::Query(Query::default()))
+            .send_and_report_err()
+    };
+    let (a, b): (crate::Result<Value>, _) = tokio::join!(send(), send());
+    a.unwrap();
+    b.unwrap();
+    assert_eq!(*server.refreshes.lock().unwrap(), 2);
+}
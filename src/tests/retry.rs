@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::api::error::{ApiError, ErrorCode};
+use crate::retry::RetryPolicy;
+use crate::Error;
+
+fn api_error(code: &str) -> Error {
+    Error::MediaWiki(
+        ApiError {
+            code: code.into(),
+            info: String::new(),
+            docref: None,
+            module: None,
+            data: None,
+        }
+        .into(),
+    )
+}
+
+#[test]
+fn backoff() {
+    let policy = RetryPolicy::default()
+        .maxlag(5)
+        .backoff(Duration::from_secs(1), Duration::from_secs(5));
+    let maxlag = api_error("maxlag");
+    assert!(maxlag.is_code(&ErrorCode::MaxLag));
+
+    assert_eq!(policy.delay(0, &maxlag, None), Some(Duration::from_secs(1)));
+    assert_eq!(policy.delay(2, &maxlag, None), Some(Duration::from_secs(4)));
+    // capped by the maximum backoff
+    assert_eq!(
+        policy.delay(1, &maxlag, Some(Duration::from_secs(30))),
+        Some(Duration::from_secs(5))
+    );
+    assert_eq!(policy.delay(3, &maxlag, None), None);
+    assert_eq!(policy.delay(0, &api_error("badtoken"), None), None);
+    assert_eq!(RetryPolicy::none().delay(0, &maxlag, None), None);
+}
+
+#[test]
+fn writes() {
+    let policy = RetryPolicy::default();
+    let bad_gateway = Error::HttpStatus(StatusCode::BAD_GATEWAY);
+    let unavailable = Error::HttpStatus(StatusCode::SERVICE_UNAVAILABLE);
+    // the edit may have been saved before the gateway gave up
+    assert!(policy.delay(0, &bad_gateway, None).is_some());
+    assert_eq!(policy.write_delay(0, &bad_gateway, None), None);
+    assert!(policy.write_delay(0, &unavailable, None).is_some());
+    assert!(policy.write_delay(0, &api_error("maxlag"), None).is_some());
+}
@@ -0,0 +1,82 @@
+use serde_json::json;
+
+use crate::siteinfo::SiteInfo;
+use crate::types::Namespace;
+
+fn frwiki() -> SiteInfo {
+    serde_json::from_value(json!({
+        "general": {
+            "mainpage": "Wikipédia:Accueil principal",
+            "sitename": "Wikipedia",
+            "wikiid": "frwiki",
+            "lang": "fr",
+            "case": "first-letter",
+            "server": "//fr.wikipedia.org",
+            "articlepath": "/wiki/$1",
+            "scriptpath": "/w",
+            "script": "/w/index.php",
+            "generator": "MediaWiki 1.43.0-wmf.1"
+        },
+        "namespaces": {
+            "0": { "id": 0, "case": "first-letter", "name": "", "subpages": false, "content": true },
+            "4": {
+                "id": 4,
+                "case": "first-letter",
+                "name": "Wikipédia",
+                "canonical": "Project",
+                "subpages": true
+            }
+        },
+        "namespacealiases": [{ "id": 4, "alias": "WP" }],
+        "interwikimap": [
+            { "prefix": "en", "local": true, "language": "English", "url": "https://en.wikipedia.org/wiki/$1" }
+        ],
+        "extensions": [{ "type": "parserhook", "name": "Cite" }],
+        "magicwords": [{ "name": "redirect", "aliases": ["#REDIRECTION", "#REDIRECT"] }]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn lookups() {
+    let info = frwiki();
+    for name in ["wikipédia", "Project", "WP"] {
+        assert_eq!(info.namespace_by_name(name).unwrap().id, Namespace::PROJECT);
+    }
+    assert!(info.namespace_by_name("Talk").is_none());
+    assert!(!info.is_case_sensitive());
+    assert_eq!(
+        info.interwiki("EN").unwrap().language.as_deref(),
+        Some("English")
+    );
+    assert_eq!(
+        info.magic_word("redirect").unwrap().aliases[0],
+        "#REDIRECTION"
+    );
+    assert!(info.has_extension("Cite"));
+}
+
+#[test]
+fn urls() {
+    let info = frwiki();
+    assert_eq!(
+        info.page_url("Wikipédia:Accueil principal")
+            .unwrap()
+            .as_str(),
+        "https://fr.wikipedia.org/wiki/Wikip%C3%A9dia:Accueil_principal"
+    );
+    assert_eq!(
+        info.page_url("AT&T?").unwrap().as_str(),
+        "https://fr.wikipedia.org/wiki/AT%26T%3F"
+    );
+    assert_eq!(
+        info.diff_url(1, 2).unwrap().as_str(),
+        "https://fr.wikipedia.org/w/index.php?diff=2&oldid=1"
+    );
+    assert_eq!(
+        info.log_url("block", Some("User:Example"))
+            .unwrap()
+            .as_str(),
+        "https://fr.wikipedia.org/w/index.php?title=Special%3ALog&type=block&page=User%3AExample"
+    );
+}
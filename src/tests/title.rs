@@ -0,0 +1,96 @@
+use serde_json::json;
+
+use crate::req::PageSpec;
+use crate::siteinfo::SiteInfo;
+use crate::title::{Title, TitleError};
+use crate::types::Namespace;
+
+fn enwiki() -> SiteInfo {
+    let ns = |id: i32, name: &str, case: &str| json!({ "id": id, "case": case, "name": name, "canonical": name });
+    serde_json::from_value(json!({
+        "general": {
+            "mainpage": "Main Page",
+            "sitename": "Wikipedia",
+            "wikiid": "enwiki",
+            "lang": "en",
+            "case": "first-letter",
+            "server": "//en.wikipedia.org",
+            "articlepath": "/wiki/$1",
+            "scriptpath": "/w",
+            "script": "/w/index.php",
+            "generator": "MediaWiki 1.43.0-wmf.1"
+        },
+        "namespaces": {
+            "-1": ns(-1, "Special", "first-letter"),
+            "0": { "id": 0, "case": "first-letter", "name": "" },
+            "1": ns(1, "Talk", "first-letter"),
+            "4": { "id": 4, "case": "first-letter", "name": "Wikipedia", "canonical": "Project" },
+            "5": { "id": 5, "case": "first-letter", "name": "Wikipedia talk", "canonical": "Project talk" },
+            "100": ns(100, "Lowercase", "case-sensitive"),
+        },
+        "namespacealiases": [{ "id": 4, "alias": "WP" }],
+        "interwikimap": [{ "prefix": "fr", "url": "https://fr.wikipedia.org/wiki/$1" }],
+    }))
+    .unwrap()
+}
+
+#[test]
+fn normalize() {
+    let info = enwiki();
+    let t = Title::new("  wp:_sandbox__page#Section", &info).unwrap();
+    assert_eq!(t.namespace(), Namespace::PROJECT);
+    assert_eq!(t.text(), "Sandbox page");
+    assert_eq!(t.to_string(), "Wikipedia:Sandbox page");
+    assert_eq!(t.dbkey(), "Wikipedia:Sandbox_page");
+    assert_eq!(t, Title::new("Project:Sandbox page", &info).unwrap());
+
+    assert_eq!(
+        Title::new(":main page", &info).unwrap().to_string(),
+        "Main page"
+    );
+    assert_eq!(
+        Title::new("Foo: bar", &info).unwrap().namespace(),
+        Namespace::MAIN
+    );
+    assert_eq!(Title::new("lowercase:foo", &info).unwrap().text(), "foo");
+    let spec = PageSpec::from(Title::new("talk:x", &info).unwrap());
+    assert!(matches!(spec, PageSpec::Title(t) if t == "Talk:X"));
+}
+
+#[test]
+fn invalid() {
+    let info = enwiki();
+    let err = |s| Title::new(s, &info).unwrap_err();
+    assert_eq!(err(""), TitleError::Empty);
+    assert_eq!(err("Talk:"), TitleError::Empty);
+    assert_eq!(err("A[b]"), TitleError::IllegalChar('['));
+    assert_eq!(err("A%20b"), TitleError::PercentEncoded);
+    assert_eq!(err("../x"), TitleError::Relative);
+    assert_eq!(err("fr:Accueil"), TitleError::Interwiki("fr".into()));
+    assert_eq!(err(&"x".repeat(256)), TitleError::TooLong);
+}
+
+#[test]
+fn talk_pages() {
+    let info = enwiki();
+    let t = Title::new("WP:Sandbox", &info).unwrap();
+    let talk = t.talk_page(&info).unwrap();
+    assert_eq!(talk.to_string(), "Wikipedia talk:Sandbox");
+    assert!(talk.is_talk_page());
+    assert_eq!(talk.talk_page(&info).unwrap(), talk);
+    assert_eq!(talk.subject_page(&info), t);
+    assert_eq!(t.subject_page(&info), t);
+    assert!(Title::new("Special:Random", &info)
+        .unwrap()
+        .talk_page(&info)
+        .is_none());
+    // namespace 101 does not exist
+    assert!(Title::new("Lowercase:x", &info)
+        .unwrap()
+        .talk_page(&info)
+        .is_none());
+    assert_eq!(
+        talk.full_url(&info).unwrap().as_str(),
+        "https://en.wikipedia.org/wiki/Wikipedia_talk:Sandbox"
+    );
+}
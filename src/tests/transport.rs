@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use futures_util::TryStreamExt;
+use reqwest::header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE, USER_AGENT};
+use serde_json::{json, Value};
+
+use crate::api::{BoxFuture, RequestBuilderExt, RequestMethod};
+use crate::req::{Action, GlobalParams, Main, Query};
+use crate::transport::{Body, HttpRequest, HttpResponse, Method, StatusCode, Transport};
+use crate::ClientBuilder;
+
+#[derive(Default)]
+struct Fake {
+    requests: Mutex<Vec<HttpRequest>>,
+    responses: Mutex<VecDeque<Value>>,
+    headers: HeaderMap,
+}
+
+impl Transport for Fake {
+    fn send(&self, req: HttpRequest) -> BoxFuture<crate::Result<HttpResponse>> {
+        self.requests.lock().unwrap().push(req);
+        let body = self.responses.lock().unwrap().pop_front().unwrap();
+        let headers = self.headers.clone();
+        Box::pin(async move {
+            Ok(HttpResponse {
+                status: StatusCode::OK,
+                headers,
+                body: Bytes::from(body.to_string()),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+async fn query_all_continues() {
+    let fake = Arc::new(Fake::default());
+    fake.responses.lock().unwrap().extend([
+        json!({ "continue": { "rccontinue": "abc", "continue": "-||" }, "query": { "n": 1 } }),
+        json!({ "batchcomplete": true, "query": { "n": 2 } }),
+    ]);
+    let client = ClientBuilder::new("https://example.org/w/api.php")
+        .transport(fake.clone())
+        .build()
+        .unwrap();
+
+    let res: Vec<Value> = client
+        .query_all(Query::default())
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[1]["query"]["n"], 2);
+
+    let requests = fake.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[0].url.as_str().contains("rccontinue"));
+    assert!(requests[1].url.as_str().contains("rccontinue=abc"));
+}
+
+#[tokio::test]
+async fn long_get_is_posted() {
+    let fake = Arc::new(Fake::default());
+    fake.responses.lock().unwrap().extend([
+        json!({ "continue": { "rvcontinue": "abc", "continue": "-||" }, "query": {} }),
+        json!({ "batchcomplete": true, "query": {} }),
+    ]);
+    let client = ClientBuilder::new("https://example.org/w/api.php")
+        .transport(fake.clone())
+        .max_url_length(200)
+        .build()
+        .unwrap();
+
+    let titles: Vec<_> = (0..50).map(|i| format!("Page {i}")).collect();
+    let q = Query {
+        titles: Some(titles),
+        ..Default::default()
+    };
+    let res: Vec<Value> = client.query_all(q).try_collect().await.unwrap();
+    assert_eq!(res.len(), 2);
+
+    let requests = fake.requests.lock().unwrap();
+    for req in requests.iter() {
+        assert_eq!(req.method, Method::POST);
+        assert_eq!(req.url.query(), None);
+    }
+    let Body::Form(body) = &requests[1].body else {
+        panic!("expected a form body");
+    };
+    assert!(body.contains("titles=Page%200%7CPage%201"));
+    assert!(body.contains("rvcontinue=abc"));
+}
+
+#[tokio::test]
+async fn global_params() {
+    let fake = Arc::new(Fake::default());
+    fake.responses.lock().unwrap().push_back(json!({
+        "batchcomplete": true,
+        "curtimestamp": "2024-01-01T00:00:00Z",
+        "servedby": "mw1234",
+        "query": {},
+    }));
+    let client = ClientBuilder::new("https://example.org/w/api.php")
+        .transport(fake.clone())
+        .global_params(GlobalParams {
+            variant: Some("zh-hans".into()),
+            curtimestamp: Some(true),
+            ..Default::default()
+        })
+        .uselang("en")
+        .build()
+        .unwrap();
+
+    let mut main = Main::query(Query::default());
+    main.global.servedby = Some(true);
+    let res = client
+        .request(RequestMethod::Get, main)
+        .send_and_report()
+        .await
+        .unwrap();
+    assert_eq!(res.meta.servedby.as_deref(), Some("mw1234"));
+    assert_eq!(res.meta.curtimestamp.unwrap().0.timestamp(), 1704067200);
+    assert!(res.body.get("servedby").is_none());
+
+    let url = fake.requests.lock().unwrap()[0].url.clone();
+    assert_eq!(
+        url.query(),
+        Some("action=query&format=json&formatversion=2&uselang=en&variant=zh-hans&curtimestamp=&servedby=")
+    );
+}
+
+#[tokio::test]
+async fn request_overrides_global_params() {
+    let fake = Arc::new(Fake::default());
+    fake.responses
+        .lock()
+        .unwrap()
+        .push_back(json!({ "batchcomplete": true, "query": {} }));
+    let client = ClientBuilder::new("https://example.org/w/api.php")
+        .transport(fake.clone())
+        .global_params(GlobalParams {
+            curtimestamp: Some(true),
+            servedby: Some(true),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+
+    let mut main = Main::query(Query::default());
+    main.global.curtimestamp = Some(false);
+    client
+        .request(RequestMethod::Get, main)
+        .send_and_report()
+        .await
+        .unwrap();
+
+    let url = fake.requests.lock().unwrap()[0].url.clone();
+    assert_eq!(
+        url.query(),
+        Some("action=query&format=json&formatversion=2&servedby=")
+    );
+}
+
+#[tokio::test]
+async fn user_agent_and_cookies() {
+    let mut fake = Fake::default();
+    fake.headers.insert(
+        SET_COOKIE,
+        HeaderValue::from_static("session=abc; Path=/; HttpOnly"),
+    );
+    let fake = Arc::new(fake);
+    fake.responses.lock().unwrap().extend([
+        json!({ "batchcomplete": true, "query": {} }),
+        json!({ "batchcomplete": true, "query": {} }),
+    ]);
+    let client = ClientBuilder::new("https://example.org/w/api.php")
+        .transport(fake.clone())
+        .user_agent("TestBot/1.0")
+        .build()
+        .unwrap();
+    assert!(client.reqwest_client().is_none());
+
+    for _ in 0..2 {
+        client
+            .get(Action::Query(Query::default()))
+            .send_and_report()
+            .await
+            .unwrap();
+    }
+
+    let requests = fake.requests.lock().unwrap();
+    for req in requests.iter() {
+        assert_eq!(req.headers[USER_AGENT], "TestBot/1.0");
+    }
+    assert!(requests[0].headers.get(COOKIE).is_none());
+    assert_eq!(requests[1].headers[COOKIE], "session=abc");
+}
+
+#[tokio::test]
+async fn unknown_edit_result() {
+    let fake = Arc::new(Fake::default());
+    fake.responses.lock().unwrap().extend([
+        json!({ "query": { "tokens": { "csrftoken": "abc+\\" } } }),
+        json!({ "edit": { "result": "Spamblacklist", "spamblacklist": "example.com" } }),
+    ]);
+    let client = ClientBuilder::new("https://example.org/w/api.php")
+        .transport(fake.clone())
+        .build()
+        .unwrap();
+
+    let err = client
+        .build_edit("Sandbox")
+        .text("https://example.com")
+        .send()
+        .await
+        .unwrap_err();
+    let crate::Error::EditFailed(edit) = err else {
+        panic!("expected an edit failure, got {err:?}");
+    };
+    assert_eq!(edit.result, crate::res::EditStatus::Unknown);
+    assert_eq!(edit.raw["spamblacklist"], "example.com");
+}
@@ -1,12 +1,11 @@
 use chrono::DateTime;
 
 use crate::api::mkurl;
-use crate::req::{EditBuilder, Main};
+use crate::req::{Assert, EditBuilder, Main, TokenType};
 use crate::types::MwTimestamp;
 
 #[test]
 fn edit() {
-    
     let t = MwTimestamp(DateTime::from_timestamp(0, 0).unwrap());
     let main = Main::edit(
         EditBuilder::new()
@@ -55,3 +54,16 @@ fn edit() {
         u.to_string()
     )
 }
+
+#[test]
+fn assert() {
+    let mut main = Main::tokens(TokenType::CSRF);
+    main.assert = Some(Assert::Bot);
+    main.assertuser = Some("Example bot".into());
+    let u = mkurl("https://en.wikipedia.org/w/api.php".parse().unwrap(), main);
+    assert_eq!(
+        "https://en.wikipedia.org/w/api.php?action=query&meta=tokens&type=csrf&\
+    format=json&formatversion=2&assert=bot&assertuser=Example%20bot",
+        u.to_string()
+    )
+}
@@ -0,0 +1,210 @@
+//! Page titles, normalized the way MediaWiki does so that `Foo bar`, `foo_bar` and `Foo_bar` are
+//! the same page.
+
+use std::fmt;
+
+use reqwest::Url;
+
+use crate::req::PageSpec;
+use crate::siteinfo::SiteInfo;
+use crate::types::Namespace;
+use crate::Result;
+
+/// Characters that can never be part of a title.
+const ILLEGAL: &[char] = &['#', '<', '>', '[', ']', '|', '{', '}', '\u{fffd}'];
+
+/// The longest title, in bytes, without the namespace prefix.
+const MAX_LEN: usize = 255;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum TitleError {
+    #[error("title is empty")]
+    Empty,
+    #[error("title contains the illegal character {0:?}")]
+    IllegalChar(char),
+    #[error("title contains a percent-encoded character")]
+    PercentEncoded,
+    #[error("title is longer than 255 bytes")]
+    TooLong,
+    #[error("title is a relative path")]
+    Relative,
+    #[error("title points to another wiki with the interwiki prefix {0:?}")]
+    Interwiki(String),
+    #[error("no namespace with id {0}")]
+    NoSuchNamespace(Namespace),
+}
+
+/// A normalized title on a particular wiki.
+///
+/// Created with [`Title::new`], which resolves the namespace prefix against the
+/// [`SiteInfo`] of the wiki.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Title {
+    namespace: Namespace,
+    /// The page name without the namespace, with spaces.
+    text: String,
+    /// The local name of the namespace.
+    prefix: String,
+}
+
+impl Title {
+    /// Parse and normalize a title such as `wp:sandbox`: underscores become spaces, the namespace
+    /// prefix is resolved, including aliases, and the first letter is capitalized if the
+    /// namespace is not case-sensitive.
+    pub fn new(title: &str, site: &SiteInfo) -> Result<Self, TitleError> {
+        let title = normalize_spaces(title);
+        let title = title.split('#').next().unwrap_or_default().trim();
+        let title = title.strip_prefix(':').unwrap_or(title).trim_start();
+
+        let (namespace, text) = match title.split_once(':') {
+            Some((prefix, rest)) => match site.namespace_by_name(prefix.trim_end()) {
+                Some(ns) => (ns.id, rest.trim_start()),
+                None if site.interwiki(prefix.trim_end()).is_some() => {
+                    return Err(TitleError::Interwiki(prefix.trim_end().to_owned()))
+                }
+                None => (Namespace::MAIN, title),
+            },
+            None => (Namespace::MAIN, title),
+        };
+        Self::with_namespace(namespace, text, site)
+    }
+
+    /// A title in a namespace given by id, with `text` being the page name without a prefix.
+    pub fn with_namespace(
+        namespace: Namespace,
+        text: &str,
+        site: &SiteInfo,
+    ) -> Result<Self, TitleError> {
+        let ns = site
+            .namespaces
+            .get(&namespace)
+            .ok_or(TitleError::NoSuchNamespace(namespace))?;
+        let text = normalize_spaces(text);
+        validate(&text)?;
+        if namespace != Namespace::MAIN && text.starts_with(':') {
+            return Err(TitleError::IllegalChar(':'));
+        }
+        let text = if ns.case == "first-letter" {
+            let mut chars = text.chars();
+            let first = chars.next().expect("validated to be non-empty");
+            first.to_uppercase().chain(chars).collect()
+        } else {
+            text
+        };
+        Ok(Self {
+            namespace,
+            text,
+            prefix: ns.name.clone(),
+        })
+    }
+
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    /// The page name without the namespace prefix, such as `Sandbox` for `Wikipedia:Sandbox`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The title with the namespace prefix and spaces.
+    pub fn full_text(&self) -> String {
+        self.to_string()
+    }
+
+    /// The title with the namespace prefix and underscores, as used in URLs.
+    pub fn dbkey(&self) -> String {
+        self.full_text().replace(' ', "_")
+    }
+
+    pub fn is_talk_page(&self) -> bool {
+        self.namespace.is_talk()
+    }
+
+    /// The talk page of this page, or the page itself if it is a talk page. Special pages have no
+    /// talk pages.
+    pub fn talk_page(&self, site: &SiteInfo) -> Option<Self> {
+        Self::with_namespace(self.namespace.talk()?, &self.text, site).ok()
+    }
+
+    /// The page a talk page is about, or the page itself if it is not a talk page.
+    pub fn subject_page(&self, site: &SiteInfo) -> Self {
+        Self::with_namespace(self.namespace.subject(), &self.text, site)
+            .unwrap_or_else(|_| self.clone())
+    }
+
+    /// The URL to view the page.
+    pub fn full_url(&self, site: &SiteInfo) -> Result<Url> {
+        site.page_url(&self.full_text())
+    }
+}
+
+impl fmt::Display for Title {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.prefix.is_empty() {
+            f.write_str(&self.text)
+        } else {
+            write!(f, "{}:{}", self.prefix, self.text)
+        }
+    }
+}
+
+impl From<Title> for PageSpec {
+    fn from(title: Title) -> Self {
+        Self::Title(title.full_text())
+    }
+}
+
+impl From<&Title> for PageSpec {
+    fn from(title: &Title) -> Self {
+        Self::Title(title.full_text())
+    }
+}
+
+impl From<Title> for String {
+    fn from(title: Title) -> Self {
+        title.full_text()
+    }
+}
+
+/// Turn underscores and runs of whitespace into single spaces, and trim.
+fn normalize_spaces(s: &str) -> String {
+    s.split(|c: char| c == '_' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn validate(text: &str) -> Result<(), TitleError> {
+    if text.is_empty() {
+        return Err(TitleError::Empty);
+    }
+    if let Some(c) = text.chars().find(|c| ILLEGAL.contains(c) || c.is_control()) {
+        return Err(TitleError::IllegalChar(c));
+    }
+    let bytes = text.as_bytes();
+    if bytes
+        .windows(3)
+        .any(|w| w[0] == b'%' && w[1].is_ascii_hexdigit() && w[2].is_ascii_hexdigit())
+    {
+        return Err(TitleError::PercentEncoded);
+    }
+    if text.len() > MAX_LEN {
+        return Err(TitleError::TooLong);
+    }
+    let relative = text == "."
+        || text == ".."
+        || text.starts_with("./")
+        || text.starts_with("../")
+        || text.contains("/./")
+        || text.contains("/../")
+        || text.ends_with("/.")
+        || text.ends_with("/..");
+    if relative {
+        return Err(TitleError::Relative);
+    }
+    if text.contains("~~~") {
+        return Err(TitleError::IllegalChar('~'));
+    }
+    Ok(())
+}
@@ -0,0 +1,290 @@
+//! The HTTP layer used by [`Client`](crate::Client).
+//!
+//! Every request goes through a [`Transport`]. The default is [`ReqwestTransport`], but anything
+//! that can turn an [`HttpRequest`] into an [`HttpResponse`] can be plugged in with
+//! [`ClientBuilder::transport`](crate::ClientBuilder::transport), such as middleware that wraps
+//! another transport, or an in-process fake for tests.
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::{Stream, TryStreamExt};
+use reqwest::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, COOKIE, SET_COOKIE, USER_AGENT,
+};
+use reqwest::Url;
+pub use reqwest::{Method, StatusCode};
+
+use crate::api::BoxFuture;
+use crate::cookies;
+use crate::credentials::{redact_fields, redact_url};
+use crate::Result;
+
+pub mod cassette;
+
+/// A request to send, independent of the HTTP client.
+///
+/// The `Debug` output leaves out tokens, passwords and the `Authorization` header.
+#[derive(Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub body: Body,
+}
+
+impl HttpRequest {
+    pub fn get(url: Url) -> Self {
+        Self {
+            method: Method::GET,
+            url,
+            headers: HeaderMap::new(),
+            body: Body::Empty,
+        }
+    }
+
+    pub fn post(url: Url, body: Body) -> Self {
+        Self {
+            method: Method::POST,
+            url,
+            headers: HeaderMap::new(),
+            body,
+        }
+    }
+}
+
+impl fmt::Debug for HttpRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut headers = self.headers.clone();
+        for name in [AUTHORIZATION, COOKIE] {
+            if let Some(value) = headers.get_mut(name) {
+                value.set_sensitive(true);
+            }
+        }
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &redact_url(&self.url).as_str())
+            .field("headers", &headers)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum Body {
+    #[default]
+    Empty,
+    /// An `application/x-www-form-urlencoded` body.
+    Form(String),
+    /// The text fields of a `multipart/form-data` body.
+    Multipart(Vec<(String, String)>),
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("Empty"),
+            Self::Form(body) => {
+                let fields: Vec<_> = ::url::form_urlencoded::parse(body.as_bytes()).collect();
+                let fields = redact_fields(fields.iter().map(|(k, v)| (&**k, &**v)));
+                f.debug_tuple("Form").field(&fields).finish()
+            }
+            Self::Multipart(fields) => {
+                let fields = redact_fields(fields.iter().map(|(k, v)| (&**k, &**v)));
+                f.debug_tuple("Multipart").field(&fields).finish()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes>> + Send>>;
+
+#[cfg(target_arch = "wasm32")]
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes>>>>;
+
+/// Sends HTTP requests for a [`Client`](crate::Client).
+pub trait Transport: Send + Sync + 'static {
+    fn send(&self, req: HttpRequest) -> BoxFuture<Result<HttpResponse>>;
+
+    /// Send a request and stream the response body as it arrives, for server-sent events.
+    ///
+    /// The default implementation waits for the whole body.
+    fn stream(&self, req: HttpRequest) -> BoxFuture<Result<ByteStream>> {
+        let res = self.send(req);
+        Box::pin(async move {
+            let body = res.await?.body;
+            let stream: ByteStream = Box::pin(futures_util::stream::once(async { Ok(body) }));
+            Ok(stream)
+        })
+    }
+
+    /// The reqwest client behind this transport, if there is one.
+    fn reqwest_client(&self) -> Option<&reqwest::Client> {
+        None
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Arc<T> {
+    fn send(&self, req: HttpRequest) -> BoxFuture<Result<HttpResponse>> {
+        (**self).send(req)
+    }
+
+    fn stream(&self, req: HttpRequest) -> BoxFuture<Result<ByteStream>> {
+        (**self).stream(req)
+    }
+
+    fn reqwest_client(&self) -> Option<&reqwest::Client> {
+        (**self).reqwest_client()
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn send(&self, req: HttpRequest) -> BoxFuture<Result<HttpResponse>> {
+        (**self).send(req)
+    }
+
+    fn stream(&self, req: HttpRequest) -> BoxFuture<Result<ByteStream>> {
+        (**self).stream(req)
+    }
+
+    fn reqwest_client(&self) -> Option<&reqwest::Client> {
+        (**self).reqwest_client()
+    }
+}
+
+/// The default transport, sending requests with [`reqwest`].
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    fn build(&self, req: HttpRequest) -> reqwest::RequestBuilder {
+        let HttpRequest {
+            method,
+            url,
+            headers,
+            body,
+        } = req;
+        let r = self.client.request(method, url).headers(headers);
+        match body {
+            Body::Empty => r,
+            Body::Form(body) => r
+                .header(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                )
+                .body(body),
+            Body::Multipart(fields) => r.multipart(
+                fields
+                    .into_iter()
+                    .fold(reqwest::multipart::Form::new(), |f, (k, v)| f.text(k, v)),
+            ),
+        }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, req: HttpRequest) -> BoxFuture<Result<HttpResponse>> {
+        let req = self.build(req);
+        Box::pin(async move {
+            let res = req.send().await?;
+            Ok(HttpResponse {
+                status: res.status(),
+                headers: res.headers().clone(),
+                body: res.bytes().await?,
+            })
+        })
+    }
+
+    fn stream(&self, req: HttpRequest) -> BoxFuture<Result<ByteStream>> {
+        let req = self.build(req);
+        Box::pin(async move {
+            let res = req.send().await?.error_for_status()?;
+            let stream: ByteStream = Box::pin(res.bytes_stream().map_err(Into::into));
+            Ok(stream)
+        })
+    }
+
+    fn reqwest_client(&self) -> Option<&reqwest::Client> {
+        Some(&self.client)
+    }
+}
+
+/// Wraps a custom transport to send the user agent and keep the cookie jar, as the default
+/// transport does.
+pub(crate) struct SessionTransport {
+    pub(crate) inner: Arc<dyn Transport>,
+    pub(crate) user_agent: HeaderValue,
+    pub(crate) cookies: cookies::Jar,
+}
+
+impl SessionTransport {
+    fn prepare(&self, req: &mut HttpRequest) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            use reqwest::cookie::CookieStore;
+
+            req.headers
+                .entry(USER_AGENT)
+                .or_insert_with(|| self.user_agent.clone());
+            if !req.headers.contains_key(COOKIE) {
+                if let Some(cookies) = self.cookies.cookies(&req.url) {
+                    req.headers.insert(COOKIE, cookies);
+                }
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        req.headers
+            .entry("Api-User-Agent")
+            .or_insert_with(|| self.user_agent.clone());
+    }
+}
+
+impl Transport for SessionTransport {
+    #[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+    fn send(&self, mut req: HttpRequest) -> BoxFuture<Result<HttpResponse>> {
+        self.prepare(&mut req);
+        let url = req.url.clone();
+        let cookies = self.cookies.clone();
+        let res = self.inner.send(req);
+        Box::pin(async move {
+            let res = res.await?;
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                use reqwest::cookie::CookieStore;
+
+                cookies.set_cookies(&mut res.headers.get_all(SET_COOKIE).iter(), &url);
+            }
+            Ok(res)
+        })
+    }
+
+    fn stream(&self, mut req: HttpRequest) -> BoxFuture<Result<ByteStream>> {
+        self.prepare(&mut req);
+        self.inner.stream(req)
+    }
+
+    fn reqwest_client(&self) -> Option<&reqwest::Client> {
+        self.inner.reqwest_client()
+    }
+}
@@ -0,0 +1,229 @@
+//! Recording API interactions to a file and replaying them, so that tests can run without a
+//! network connection.
+//!
+//! ```no_run
+//! use wiki::transport::cassette::Cassette;
+//! use wiki::transport::ReqwestTransport;
+//! use wiki::ClientBuilder;
+//!
+//! # fn main() -> wiki::Result<()> {
+//! // replays `tests/cassettes/search.json` if it exists, otherwise records it.
+//! let cassette = Cassette::auto("tests/cassettes/search.json", ReqwestTransport::default())?;
+//! let client = ClientBuilder::enwiki().transport(cassette).build()?;
+//! # let _ = client;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, SET_COOKIE};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::{Body, HttpRequest, HttpResponse, StatusCode, Transport};
+use crate::api::BoxFuture;
+use crate::credentials::{is_secret_param, redact_json};
+use crate::{Error, Result};
+
+/// A recorded response, together with the key of the request it answered.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Interaction {
+    pub key: String,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Tape {
+    interactions: Vec<Interaction>,
+}
+
+enum Mode {
+    Record {
+        inner: Box<dyn Transport>,
+        recorded: Arc<Mutex<Vec<Interaction>>>,
+    },
+    Replay(Mutex<HashMap<String, VecDeque<Interaction>>>),
+}
+
+/// A [`Transport`] that records interactions to a file, or replays them from it.
+///
+/// Requests are matched by [`request_key`], which leaves out tokens, timestamps and passwords,
+/// so a replayed session does not depend on the tokens handed out while recording. Requests
+/// with the same key are answered in the order they were recorded. Tokens in recorded responses
+/// are redacted, so cassettes can be committed.
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+}
+
+impl Cassette {
+    /// Send requests with `inner` and record them. The cassette is written when it is dropped,
+    /// or with [`Cassette::save`].
+    pub fn record(path: impl Into<PathBuf>, inner: impl Transport) -> Self {
+        Self {
+            path: path.into(),
+            mode: Mode::Record {
+                inner: Box::new(inner),
+                recorded: Default::default(),
+            },
+        }
+    }
+
+    /// Answer requests from a recorded cassette without touching the network.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let tape: Tape = serde_json::from_reader(BufReader::new(File::open(&path)?))?;
+        let mut map: HashMap<_, VecDeque<_>> = HashMap::new();
+        for i in tape.interactions {
+            map.entry(i.key.clone()).or_default().push_back(i);
+        }
+        Ok(Self {
+            path,
+            mode: Mode::Replay(Mutex::new(map)),
+        })
+    }
+
+    /// Replay the cassette if it exists, otherwise record it with `inner`.
+    pub fn auto(path: impl Into<PathBuf>, inner: impl Transport) -> Result<Self> {
+        let path = path.into();
+        if path.exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path, inner))
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_recording(&self) -> bool {
+        matches!(self.mode, Mode::Record { .. })
+    }
+
+    /// Write the recorded interactions. Does nothing when replaying.
+    pub fn save(&self) -> Result<()> {
+        let Mode::Record { recorded, .. } = &self.mode else {
+            return Ok(());
+        };
+        let tape = Tape {
+            interactions: recorded.lock().unwrap().clone(),
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let f = BufWriter::new(File::create(&self.path)?);
+        serde_json::to_writer_pretty(f, &tape)?;
+        Ok(())
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            warn!(path = %self.path.display(), "failed to save cassette: {e}");
+        }
+    }
+}
+
+impl Transport for Cassette {
+    fn send(&self, req: HttpRequest) -> BoxFuture<Result<HttpResponse>> {
+        let key = request_key(&req);
+        match &self.mode {
+            Mode::Record { inner, recorded } => {
+                let res = inner.send(req);
+                let recorded = recorded.clone();
+                Box::pin(async move {
+                    let res = res.await?;
+                    debug!(%key, "recording");
+                    recorded.lock().unwrap().push(Interaction {
+                        key,
+                        status: res.status.as_u16(),
+                        headers: res
+                            .headers
+                            .iter()
+                            .filter(|(k, _)| **k != SET_COOKIE)
+                            .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_owned())))
+                            .collect(),
+                        body: redact_body(&res.body),
+                    });
+                    Ok(res)
+                })
+            }
+            Mode::Replay(map) => {
+                let i = map
+                    .lock()
+                    .unwrap()
+                    .get_mut(&key)
+                    .and_then(VecDeque::pop_front);
+                Box::pin(async move {
+                    let i = i.ok_or_else(|| Error::NotRecorded(key))?;
+                    let mut headers = HeaderMap::new();
+                    for (k, v) in i.headers {
+                        if let (Ok(k), Ok(v)) = (HeaderName::try_from(k), HeaderValue::try_from(v))
+                        {
+                            headers.append(k, v);
+                        }
+                    }
+                    Ok(HttpResponse {
+                        status: StatusCode::from_u16(i.status)
+                            .map_err(|_| Error::CustomStatic("invalid status in cassette"))?,
+                        headers,
+                        body: Bytes::from(i.body),
+                    })
+                })
+            }
+        }
+    }
+}
+
+/// The body to save, with tokens and other secrets in JSON responses replaced. Replaying does
+/// not need them, as requests are matched without their tokens.
+fn redact_body(body: &[u8]) -> String {
+    if let Ok(mut v) = serde_json::from_slice::<serde_json::Value>(body) {
+        if redact_json(&mut v) {
+            return v.to_string();
+        }
+    }
+    String::from_utf8_lossy(body).into_owned()
+}
+
+/// Whether a parameter changes between runs or is secret, and is left out of the key.
+fn is_volatile(name: &str) -> bool {
+    is_secret_param(name) || name.to_ascii_lowercase().ends_with("timestamp")
+}
+
+/// The key requests are matched by: the method and URL without the query, followed by the
+/// sorted parameters from both the query and the body. Volatile parameters such as tokens,
+/// timestamps and passwords are left out.
+pub fn request_key(req: &HttpRequest) -> String {
+    let mut params: Vec<(String, String)> = req.url.query_pairs().into_owned().collect();
+    match &req.body {
+        Body::Empty => {}
+        Body::Form(body) => {
+            params.extend(::url::form_urlencoded::parse(body.as_bytes()).into_owned())
+        }
+        Body::Multipart(fields) => params.extend(fields.iter().cloned()),
+    }
+    params.retain(|(k, _)| !is_volatile(k));
+    params.sort();
+
+    let mut url = req.url.clone();
+    url.set_query(None);
+    let mut key = format!("{} {url}", req.method);
+    for (i, (k, v)) in params.iter().enumerate() {
+        key.push(if i == 0 { '?' } else { '&' });
+        key.push_str(&urlencoding::encode(k));
+        key.push('=');
+        key.push_str(&urlencoding::encode(v));
+    }
+    key
+}
@@ -1,4 +1,7 @@
+use std::fmt;
+
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
 
 use crate::url::{BufferedName, TriStr, UrlParamWriter, WriteUrlValue};
 
@@ -36,6 +39,18 @@ impl serde::Serialize for NowableTime {
     }
 }
 
+impl<'de> serde::Deserialize<'de> for MwTimestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        chrono::DateTime::parse_from_rfc3339(&s)
+            .map(|t| Self(t.with_timezone(&Utc)))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 impl WriteUrlValue for MwTimestamp {
     fn ser<W: UrlParamWriter>(&self, w: BufferedName<'_, W>) -> Result<(), W::E> {
         w.write(format(&self.0).into()).map(|_| {})
@@ -62,3 +77,74 @@ impl From<chrono::DateTime<Utc>> for MwTimestamp {
         Self(x)
     }
 }
+
+/// A namespace id. The built-in namespaces and the common ones added by extensions have
+/// constants, and any other number is allowed.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Namespace(pub i32);
+
+impl Namespace {
+    pub const MEDIA: Self = Self(-2);
+    pub const SPECIAL: Self = Self(-1);
+    pub const MAIN: Self = Self(0);
+    pub const TALK: Self = Self(1);
+    pub const USER: Self = Self(2);
+    pub const USER_TALK: Self = Self(3);
+    pub const PROJECT: Self = Self(4);
+    pub const PROJECT_TALK: Self = Self(5);
+    pub const FILE: Self = Self(6);
+    pub const FILE_TALK: Self = Self(7);
+    pub const MEDIAWIKI: Self = Self(8);
+    pub const MEDIAWIKI_TALK: Self = Self(9);
+    pub const TEMPLATE: Self = Self(10);
+    pub const TEMPLATE_TALK: Self = Self(11);
+    pub const HELP: Self = Self(12);
+    pub const HELP_TALK: Self = Self(13);
+    pub const CATEGORY: Self = Self(14);
+    pub const CATEGORY_TALK: Self = Self(15);
+    pub const PORTAL: Self = Self(100);
+    pub const PORTAL_TALK: Self = Self(101);
+    pub const DRAFT: Self = Self(118);
+    pub const DRAFT_TALK: Self = Self(119);
+    pub const TIMED_TEXT: Self = Self(710);
+    pub const TIMED_TEXT_TALK: Self = Self(711);
+    pub const MODULE: Self = Self(828);
+    pub const MODULE_TALK: Self = Self(829);
+
+    pub fn is_talk(self) -> bool {
+        self.0 > 0 && self.0 % 2 == 1
+    }
+
+    /// The talk namespace of this namespace, or itself if it is one. Virtual namespaces such as
+    /// [`Namespace::SPECIAL`] have none.
+    pub fn talk(self) -> Option<Self> {
+        match self.0 {
+            ns if ns < 0 => None,
+            ns if ns % 2 == 1 => Some(self),
+            ns => Some(Self(ns + 1)),
+        }
+    }
+
+    /// The namespace that talk pages of this namespace are about, or itself if it is not a talk
+    /// namespace.
+    pub fn subject(self) -> Self {
+        if self.is_talk() {
+            Self(self.0 - 1)
+        } else {
+            self
+        }
+    }
+}
+
+impl From<i32> for Namespace {
+    fn from(id: i32) -> Self {
+        Self(id)
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
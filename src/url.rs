@@ -81,9 +81,17 @@ impl UrlParamWriter for Simple {
         if !self.0.is_empty() {
             self.0.push('&');
         }
-        self.0.push_str(&urlencoding::encode(&*name));
+        self.0.push_str(&urlencoding::encode(&name));
         self.0.push('=');
-        self.0.push_str(&urlencoding::encode(&*value));
+        self.0.push_str(&urlencoding::encode(&value));
+        Ok(())
+    }
+}
+
+impl UrlParamWriter for Vec<(String, String)> {
+    type E = Infallible;
+    fn add(&mut self, name: TriStr<'_>, value: TriStr<'_>) -> Result<(), Self::E> {
+        self.push((name.to_string(), value.to_string()));
         Ok(())
     }
 }
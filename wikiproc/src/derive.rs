@@ -31,7 +31,7 @@ impl Options {
         } else if meta.path.is_ident("prepend_all") {
             self.prepend_all = Some(meta.value()?.parse::<LitStr>()?.value())
         } else {
-            return Err(syn::Error::new(span, "invalid options"))
+            return Err(syn::Error::new(span, "invalid options"));
         }
         Ok(())
     }
@@ -45,7 +45,7 @@ impl FieldOptions {
         } else if meta.path.is_ident("flatten") {
             self.flatten = true
         } else {
-            return Err(syn::Error::new(span, "invalid meta"))
+            return Err(syn::Error::new(span, "invalid meta"));
         }
         self.verify(span)
     }
@@ -93,7 +93,7 @@ fn gen_fields(v: &VariantInfo, o: &Options) -> Ts {
                         let name = override_name.unwrap_or_else(|| {
                             let mut s = pp.clone().unwrap_or_default();
                             s.push_str(
-                                &*b.ast()
+                                &b.ast()
                                     .ident
                                     .as_ref()
                                     .unwrap()
@@ -145,7 +145,7 @@ fn variant_name(v: &VariantInfo) -> String {
 pub fn derive_write_url(s: synstructure::Structure) -> syn::Result<Ts> {
     let mut opts = Options::default();
     for attr in &s.ast().attrs {
-        if attr.path().get_ident().map_or(false, |i| i == "wp") {
+        if attr.path().get_ident().is_some_and(|i| i == "wp") {
             attr.parse_nested_meta(|pm| opts.parse(pm))?;
         }
     }